@@ -30,15 +30,20 @@
     fi
   }
 
+  # keepass-diff exits with 1 if there are differences, which is not an error here
+  keepass_diff() {
+    cargo run --release -- "$@" || [ $? -eq 1 ]
+  }
+
   echo "### Preparing tests"
   echo "# Creating temporary directory for test results"
   mkdir -p "$PWD/tmp-tests"
 
   echo "### Running verbose equality tests, depending on order"
   echo "# Run a <diff> b"
-  cargo run --release -- "$PWD/test/test.kdbx" "$PWD/test/test2.kdbx" --passwords demopass --no-color --verbose >"$PWD/tmp-tests/test-result-01.txt"
+  keepass_diff "$PWD/test/test.kdbx" "$PWD/test/test2.kdbx" --passwords demopass --no-color --verbose >"$PWD/tmp-tests/test-result-01.txt"
   echo "# Run b <diff> a"
-  cargo run --release -- "$PWD/test/test2.kdbx" "$PWD/test/test.kdbx" --passwords demopass --no-color --verbose >"$PWD/tmp-tests/test-result-02.txt"
+  keepass_diff "$PWD/test/test2.kdbx" "$PWD/test/test.kdbx" --passwords demopass --no-color --verbose >"$PWD/tmp-tests/test-result-02.txt"
 
   lines_of_run_01=$(wc -l <"$PWD/tmp-tests/test-result-01.txt")
   lines_of_run_02=$(wc -l <"$PWD/tmp-tests/test-result-02.txt")
@@ -65,9 +70,9 @@
 
   echo "### Running regular equality tests, depending on order"
   echo "# Run a <diff> b"
-  cargo run --release -- "$PWD/test/test.kdbx" "$PWD/test/test2.kdbx" --passwords demopass --no-color >"$PWD/tmp-tests/test-result-03.txt"
+  keepass_diff "$PWD/test/test.kdbx" "$PWD/test/test2.kdbx" --passwords demopass --no-color >"$PWD/tmp-tests/test-result-03.txt"
   echo "# Run b <diff> a"
-  cargo run --release -- "$PWD/test/test2.kdbx" "$PWD/test/test.kdbx" --passwords demopass --no-color >"$PWD/tmp-tests/test-result-04.txt"
+  keepass_diff "$PWD/test/test2.kdbx" "$PWD/test/test.kdbx" --passwords demopass --no-color >"$PWD/tmp-tests/test-result-04.txt"
 
   lines_of_run_03=$(wc -l <"$PWD/tmp-tests/test-result-03.txt")
   lines_of_run_04=$(wc -l <"$PWD/tmp-tests/test-result-04.txt")
@@ -94,9 +99,9 @@
 
   echo "### Running test to open KDBX 3.1 files"
   echo "# Run a <diff> b"
-  cargo run --release -- "$PWD/test/issue-24-kdbx-3.1/Test1.kdbx" "$PWD/test/issue-24-kdbx-3.1/Test2.kdbx" --password-a Test1 --password-b Test2 --no-color >"$PWD/tmp-tests/test-result-05.txt"
+  keepass_diff "$PWD/test/issue-24-kdbx-3.1/Test1.kdbx" "$PWD/test/issue-24-kdbx-3.1/Test2.kdbx" --password-a Test1 --password-b Test2 --no-color >"$PWD/tmp-tests/test-result-05.txt"
   echo "# Run b <diff> a"
-  cargo run --release -- "$PWD/test/issue-24-kdbx-3.1/Test2.kdbx" "$PWD/test/issue-24-kdbx-3.1/Test1.kdbx" --password-a Test2 --password-b Test1 --no-color >"$PWD/tmp-tests/test-result-06.txt"
+  keepass_diff "$PWD/test/issue-24-kdbx-3.1/Test2.kdbx" "$PWD/test/issue-24-kdbx-3.1/Test1.kdbx" --password-a Test2 --password-b Test1 --no-color >"$PWD/tmp-tests/test-result-06.txt"
 
   lines_of_run_05=$(wc -l <"$PWD/tmp-tests/test-result-05.txt")
   lines_of_run_06=$(wc -l <"$PWD/tmp-tests/test-result-06.txt")
@@ -121,7 +126,136 @@
   test_equal "first run should have same amount of plus lines as second run has minus lines" "$amount_of_plus_05" "$amount_of_minus_06"
   test_equal "first run should have same amount of minus lines as second run has plus lines" "$amount_of_minus_05" "$amount_of_plus_06"
 
+  echo "### Running exit code tests"
+  set +e
+  cargo run --release -- "$PWD/test/test.kdbx" "$PWD/test/test.kdbx" --passwords demopass --quiet >"$PWD/tmp-tests/test-result-07.txt"
+  exit_code_identical=$?
+  cargo run --release -- "$PWD/test/test.kdbx" "$PWD/test/test2.kdbx" --passwords demopass --quiet >"$PWD/tmp-tests/test-result-08.txt"
+  exit_code_different=$?
+  cargo run --release -- "$PWD/test/test.kdbx" "$PWD/test/test2.kdbx" --passwords wrong-password --quiet 2>/dev/null
+  exit_code_wrong_credentials=$?
+  cargo run --release -- "$PWD/test/test.kdbx" "$PWD/test/test2.kdbx" --passwords demopass --keyfiles "$PWD/test/missing.key" --quiet 2>/dev/null
+  exit_code_missing_keyfile=$?
+  cargo run --release -- "$PWD/test/test.kdbx" "$PWD/README.md" --passwords demopass --quiet 2>/dev/null
+  exit_code_corrupt_file=$?
+  cargo run --release -- "$PWD/test/test.kdbx" "$PWD/test/missing.kdbx" --passwords demopass --quiet 2>/dev/null
+  exit_code_io=$?
+  error_message=$(cargo run --release -- "$PWD/test/test.kdbx" "$PWD/test/missing.kdbx" --passwords demopass --quiet 2>&1 >/dev/null | tail -n 1)
+  set -e
+
+  test_equal "should exit with 0 for identical files" "$exit_code_identical" 0
+  test_equal "should exit with 1 for different files" "$exit_code_different" 1
+  test_equal "should exit with 3 for a wrong password" "$exit_code_wrong_credentials" 3
+  test_equal "should exit with 4 for a missing key file" "$exit_code_missing_keyfile" 4
+  test_equal "should exit with 5 for a file that is not a database" "$exit_code_corrupt_file" 5
+  test_equal "should exit with 7 if a file cannot be read" "$exit_code_io" 7
+  test_equal "should name the database that cannot be opened" "$error_message" "Error opening database B '$PWD/test/missing.kdbx': No such file or directory (os error 2)"
+  test_equal "should not print anything in quiet mode" "$(cat "$PWD/tmp-tests/test-result-08.txt")" ""
+
+  echo "### Running color tests"
+  keepass_diff "$PWD/test/test.kdbx" "$PWD/test/test2.kdbx" --passwords demopass >"$PWD/tmp-tests/test-result-09.txt"
+  keepass_diff "$PWD/test/test.kdbx" "$PWD/test/test2.kdbx" --passwords demopass --color always >"$PWD/tmp-tests/test-result-10.txt"
+  amount_of_escapes_09=$(grep -c $'\e\\[' "$PWD/tmp-tests/test-result-09.txt" || true)
+  amount_of_escapes_10=$(grep -c $'\e\\[' "$PWD/tmp-tests/test-result-10.txt" || true)
+
+  test_equal "should not use colors when redirected" "$amount_of_escapes_09" 0
+  test_gt "should use colors when forced" "$amount_of_escapes_10" 0
+
+  echo "### Running three-way tests"
+  keepass_diff "$PWD/test/test2.kdbx" "$PWD/test/test.kdbx" --base "$PWD/test/test.kdbx" --passwords demopass --no-color >"$PWD/tmp-tests/test-result-11.txt"
+  keepass_diff "$PWD/test/test2.kdbx" "$PWD/test/test2.kdbx" --base "$PWD/test/test.kdbx" --passwords demopass --no-color >"$PWD/tmp-tests/test-result-12.txt"
+  amount_of_lines_11=$(wc -l <"$PWD/tmp-tests/test-result-11.txt")
+  amount_of_changes_by_a_11=$(grep -c "^A " "$PWD/tmp-tests/test-result-11.txt" || true)
+  amount_of_lines_12=$(wc -l <"$PWD/tmp-tests/test-result-12.txt")
+  amount_of_changes_by_both_12=$(grep -c "^A+B " "$PWD/tmp-tests/test-result-12.txt" || true)
+
+  test_gt "should find changes against the base" "$amount_of_lines_11" 0
+  test_equal "should only find changes made by A" "$amount_of_changes_by_a_11" "$amount_of_lines_11"
+  test_equal "should find the same changes made by both" "$amount_of_changes_by_both_12" "$amount_of_lines_12"
+
+  echo "### Running merge tests"
+  fixtures="$PWD/test/__fixtures__"
+  keepass_diff merge "$fixtures/test.kdbx" "$fixtures/test.kdbx" "$fixtures/test2.kdbx" -o "$PWD/tmp-tests/merged-13.kdbx" --passwords demopass --no-color >/dev/null
+  set +e
+  cargo run --release -- "$PWD/tmp-tests/merged-13.kdbx" "$fixtures/test2.kdbx" --passwords demopass --quiet
+  exit_code_merged_13=$?
+  cargo run --release -- merge "$fixtures/test.kdbx" "$fixtures/test2.kdbx" "$fixtures/empty.kdbx" -o "$PWD/tmp-tests/merged-14.kdbx" --passwords demopass --quiet
+  exit_code_conflicts_14=$?
+  printf 'b\nq\n' | cargo run --release -- merge "$fixtures/test.kdbx" "$fixtures/test2.kdbx" "$fixtures/empty.kdbx" -o "$PWD/tmp-tests/merged-16.kdbx" --passwords demopass --interactive --no-color >"$PWD/tmp-tests/test-result-16.txt" 2>/dev/null
+  exit_code_quit_16=$?
+  set -e
+  keepass_diff merge "$fixtures/test.kdbx" "$fixtures/test2.kdbx" "$fixtures/empty.kdbx" -o "$PWD/tmp-tests/merged-15.kdbx" --passwords demopass --prefer b --no-color >"$PWD/tmp-tests/test-result-15.txt"
+  amount_of_kept_b_15=$(grep -c "kept B" "$PWD/tmp-tests/test-result-15.txt" || true)
+
+  test_equal "should apply the changes of B" "$exit_code_merged_13" 0
+  test_equal "should exit with 1 for unresolved conflicts" "$exit_code_conflicts_14" 1
+  test_equal "should not write a database with unresolved conflicts" "$(test -e "$PWD/tmp-tests/merged-14.kdbx" && echo yes || echo no)" no
+  test_gt "should resolve conflicts with the preferred database" "$amount_of_kept_b_15" 0
+  test_equal "should exit with 1 when quitting an interactive merge" "$exit_code_quit_16" 1
+  test_equal "should not write a database when quitting an interactive merge" "$(test -e "$PWD/tmp-tests/merged-16.kdbx" && echo yes || echo no)" no
+  test_equal "should ask for every conflict until quitting" "$(grep -c "^Conflict " "$PWD/tmp-tests/test-result-16.txt")" 2
+
+  echo "### Running patch tests"
+  keepass_diff "$fixtures/test.kdbx" "$fixtures/test2.kdbx" --passwords demopass --changeset "$PWD/tmp-tests/changeset-17.kdbx" --changeset-password changes --quiet
+  keepass_diff patch "$PWD/tmp-tests/changeset-17.kdbx" "$fixtures/test.kdbx" -o "$PWD/tmp-tests/patched-17.kdbx" --passwords demopass --changeset-password changes --quiet
+  set +e
+  cargo run --release -- "$PWD/tmp-tests/patched-17.kdbx" "$fixtures/test2.kdbx" --passwords demopass --quiet
+  exit_code_patched_17=$?
+  cargo run --release -- patch "$PWD/tmp-tests/changeset-17.kdbx" "$fixtures/test2.kdbx" -o "$PWD/tmp-tests/patched-18.kdbx" --passwords demopass --changeset-password changes --quiet
+  exit_code_mismatch_18=$?
+  set -e
+
+  test_equal "should turn A into B with a changeset" "$exit_code_patched_17" 0
+  test_equal "should exit with 1 if the database does not match the changeset" "$exit_code_mismatch_18" 1
+  test_equal "should not write a database that does not match the changeset" "$(test -e "$PWD/tmp-tests/patched-18.kdbx" && echo yes || echo no)" no
+
+  echo "### Running sync tests"
+  cp "$fixtures/test.kdbx" "$PWD/tmp-tests/synced-19.kdbx"
+  chmod 600 "$PWD/tmp-tests/synced-19.kdbx"
+  ln -s synced-19.kdbx "$PWD/tmp-tests/link-30.kdbx"
+  keepass_diff sync "$PWD/tmp-tests/link-30.kdbx" "$fixtures/test2.kdbx" --passwords demopass --no-color >"$PWD/tmp-tests/test-result-19.txt"
+  keepass_diff "$PWD/tmp-tests/synced-19.kdbx" "$fixtures/test2.kdbx" --passwords demopass --no-color >"$PWD/tmp-tests/test-result-20.txt"
+  keepass_diff sync "$PWD/tmp-tests/synced-19.kdbx" "$fixtures/test2.kdbx" --passwords demopass --no-color >"$PWD/tmp-tests/test-result-21.txt"
+
+  test_equal "should copy what only exists in B" "$(grep -c "^+ " "$PWD/tmp-tests/test-result-19.txt")" 1
+  test_equal "should leave nothing that only exists in B at the same path" "$(grep -c "^+ \[Root, One more\]" "$PWD/tmp-tests/test-result-20.txt" || true)" 0
+  test_equal "should keep the permissions of the synced file" "$(ls -l "$PWD/tmp-tests/synced-19.kdbx" | cut -c 1-10)" "-rw-------"
+  test_equal "should write the file a symbolic link points to" "$(test -L "$PWD/tmp-tests/link-30.kdbx" && echo yes || echo no)" yes
+  test_equal "should have nothing to sync a second time" "$(tail -n 1 "$PWD/tmp-tests/test-result-21.txt")" "Nothing to sync, nothing written"
+  set +e
+  cargo run --release -- sync "$PWD/tmp-tests/synced-19.kdbx" "$fixtures/test2.kdbx" --passwords demopass --format tree 2>/dev/null
+  exit_code_sync_format_29=$?
+  set -e
+  test_equal "should reject output options that sync cannot use" "$exit_code_sync_format_29" 2
+
+  echo "### Running git tests"
+  keepass_diff textconv "$fixtures/test.kdbx" --passwords demopass --mask-passwords >"$PWD/tmp-tests/test-result-22.txt"
+  keepass_diff git-diff vault.kdbx "$fixtures/test.kdbx" 0000000 100644 "$fixtures/test2.kdbx" 1111111 100644 --passwords demopass --no-color >"$PWD/tmp-tests/test-result-23.txt"
+  keepass_diff git-diff vault.kdbx /dev/null 0000000 000000 "$fixtures/test2.kdbx" 1111111 100644 --passwords demopass --no-color >"$PWD/tmp-tests/test-result-24.txt"
+  keepass_diff git-diff vault.kdbx "$fixtures/test.kdbx" 0000000 100644 "$fixtures/test2.kdbx" 1111111 100644 --passwords demopass --no-color --format unified >"$PWD/tmp-tests/test-result-27.txt"
+
+  test_equal "should print the same text as for a unified diff" "$(head -n 1 "$PWD/tmp-tests/test-result-22.txt")" "[Root]"
+  test_equal "should not print passwords with textconv and masking" "$(grep -c "demopass\|Password = [^*]" "$PWD/tmp-tests/test-result-22.txt" || true)" 0
+  test_equal "should name the path in the repository" "$(head -n 1 "$PWD/tmp-tests/test-result-23.txt")" "keepass-diff a/vault.kdbx b/vault.kdbx"
+  test_gt "should compare an added database with an empty one" "$(grep -c "^+ " "$PWD/tmp-tests/test-result-24.txt")" 0
+  test_equal "should take the output format after git-diff" "$(sed -n 2p "$PWD/tmp-tests/test-result-27.txt")" "--- a/vault.kdbx"
+
+  echo "### Running git revision tests"
+  set +e
+  cargo run --release -- HEAD:test/__fixtures__/test.kdbx "$fixtures/test.kdbx" --passwords demopass --quiet
+  exit_code_revision_25=$?
+  cargo run --release -- HEAD:test/__fixtures__/missing.kdbx "$fixtures/test.kdbx" --passwords demopass --quiet 2>/dev/null
+  exit_code_missing_revision_26=$?
+  error_drive_letter_28=$(cargo run --release -- 'C:\vault.kdbx' "$fixtures/test.kdbx" --passwords demopass 2>&1 >/dev/null | tail -n 1)
+  set -e
+
+  test_equal "should read a database from git" "$exit_code_revision_25" 0
+  test_equal "should exit with 7 for a path that is not in the revision" "$exit_code_missing_revision_26" 7
+  test_equal "should report a missing file for a path with a drive letter" "$error_drive_letter_28" "Error opening database A 'C:\vault.kdbx': No such file or directory (os error 2)"
+
   echo "### Testing snapshots against fixtures"
+  # colored snapshots are written to a file, so colors need to be forced
+  export CLICOLOR_FORCE=1
   for dir in test/test-*; do
     IFS='_' read -r -a files <<<"$(basename "$dir" | cut -c6-)"
     file_a=${files[0]}
@@ -132,7 +266,7 @@
 
       mkdir -p "res/$dir"
       test_result_name="$PWD/tmp-tests/snapshot-result-$(basename "$dir").txt"
-      cargo run --release -- "test/__fixtures__/${file_a}.kdbx" "test/__fixtures__/${file_b}.kdbx" "${args[@]}" >"$test_result_name"
+      keepass_diff "test/__fixtures__/${file_a}.kdbx" "test/__fixtures__/${file_b}.kdbx" "${args[@]}" >"$test_result_name"
       echo "# Run $snapshot"
       if diff "$test_result_name" "$snapshot"; then
         echo "✅ $snapshot"
@@ -0,0 +1,572 @@
+use base64::{engine::general_purpose, Engine as _};
+use keepass::config::{DatabaseConfig, KdfConfig};
+use keepass::db::{CustomDataItem, Entry, Group, History, Node, Times, Value};
+use keepass::error::DatabaseSaveError;
+use keepass::{Database, DatabaseKey};
+
+use crate::diff::changes::{EntryChange, EntryDiff, FieldChange, GroupChange, GroupDiff};
+use crate::diff::{entry, field, group};
+use crate::error::OpenError;
+use crate::locate::{entry_in, entry_in_mut, extend, group_at, is_entry, is_group};
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+/// The title of the entry that holds the changes in a changeset file
+const TITLE: &str = "keepass-diff changeset";
+/// The field of that entry with the changes as JSON
+const CHANGES: &str = "Changes";
+/// The custom data of an entry in a changeset file with the names of its binary fields as JSON.
+/// keepass can only save values as text, so binary values are written as base64.
+const BINARY_FIELDS: &str = "keepass-diff binary fields";
+
+/// The differences between two databases A and B, together with the groups and entries of B
+/// that are needed to apply them. These keep everything the differences do not show, like the
+/// kind of their values, binary values, UUIDs, times, icons and history.
+#[derive(Debug)]
+pub struct Changeset {
+    pub changes: GroupDiff,
+    /// The added groups and entries and the new versions of modified entries, inside of a group
+    /// per path of the group they belong to
+    nodes: HashMap<Vec<String>, Group>,
+}
+
+/// A part of a patch that does not match the database it is applied to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The names from the root group down to the object, ending with its own name
+    pub path: Vec<String>,
+    pub reason: String,
+}
+
+impl Changeset {
+    /// Takes the groups and entries that the differences add or modify from `b`, the root group
+    /// of database B
+    pub fn new(changes: &GroupDiff, b: &Group) -> Changeset {
+        let mut nodes = HashMap::new();
+        collect(changes, b, std::slice::from_ref(&changes.name), &mut nodes);
+        Changeset {
+            changes: changes.clone(),
+            nodes,
+        }
+    }
+
+    fn group(&self, path: &[String], name: &str) -> Option<&Group> {
+        self.nodes
+            .get(path)?
+            .children
+            .iter()
+            .find_map(|node| match node {
+                Node::Group(group) if group.name == name => Some(group),
+                _ => None,
+            })
+    }
+
+    fn entry(&self, path: &[String], uuid: &str, title: &str) -> Option<&Entry> {
+        entry_in(self.nodes.get(path)?, uuid, title)
+    }
+}
+
+fn collect(diff: &GroupDiff, b: &Group, path: &[String], nodes: &mut HashMap<Vec<String>, Group>) {
+    let source = match group_at(b, path) {
+        Some(source) => source,
+        None => return,
+    };
+    let mut container = Group::new("");
+    for change in &diff.groups {
+        match change {
+            GroupChange::Added(info) => {
+                let found = source
+                    .children
+                    .iter()
+                    .find(|node| is_group(node, &info.name));
+                container.children.extend(found.cloned());
+            }
+            GroupChange::Modified(diff) => collect(diff, b, &extend(path, &diff.name), nodes),
+            GroupChange::Removed(_) => {}
+        }
+    }
+    for change in &diff.entries {
+        let found = match change {
+            EntryChange::Added(info) => entry_in(source, &info.uuid, &info.title),
+            EntryChange::Modified(diff) => entry_in(source, &diff.uuid, &diff.title),
+            EntryChange::Removed(_) => None,
+        };
+        if let Some(entry) = found {
+            container.add_child(entry.clone());
+        }
+    }
+    if !container.children.is_empty() {
+        nodes.insert(path.to_vec(), container);
+    }
+}
+
+/// Writes the changeset into a file. A changeset is a KeePass database itself, so it is
+/// encrypted with `key` like any other database. The values are never masked, as they are
+/// needed to apply the changes.
+pub fn save(
+    changeset: &Changeset,
+    destination: &mut dyn Write,
+    key: DatabaseKey,
+) -> Result<(), DatabaseSaveError> {
+    let mut config = DatabaseConfig::default();
+    // a changeset holds every changed value, so it gets the key derivation of KeePassXC, which
+    // uses far more memory than the default of 1 MiB and is harder to brute force
+    if let KdfConfig::Argon2 {
+        iterations, memory, ..
+    } = &mut config.kdf_config
+    {
+        *iterations = 10;
+        *memory = 64 * 1024 * 1024;
+    }
+    let mut database = Database::new(config);
+    let json = serde_json::to_string(&changeset.changes).map_err(std::io::Error::from)?;
+    let mut entry = Entry::new();
+    entry
+        .fields
+        .insert("Title".to_owned(), Value::Unprotected(TITLE.to_owned()));
+    entry
+        .fields
+        .insert(CHANGES.to_owned(), Value::Protected(json.as_bytes().into()));
+    database.root.add_child(entry);
+    for (path, container) in &changeset.nodes {
+        let mut container = container.clone();
+        container.name = serde_json::to_string(path).map_err(std::io::Error::from)?;
+        for_each_entry(&mut container, &mut |entry| encode_binary_fields(entry))?;
+        database.root.add_child(container);
+    }
+    database.save(destination, key)
+}
+
+/// Reads a changeset from a file written by [`save`]
+pub fn load(source: &mut dyn Read, key: DatabaseKey) -> Result<Changeset, OpenError> {
+    let database = Database::open(source, key)?;
+    let json = database
+        .root
+        .children
+        .iter()
+        .find_map(|node| match node {
+            Node::Entry(entry) if entry.get_title() == Some(TITLE) => entry.get(CHANGES),
+            _ => None,
+        })
+        .ok_or_else(|| OpenError::CorruptFile("not a changeset".to_owned()))?;
+    let changes =
+        serde_json::from_str(json).map_err(|error| OpenError::CorruptFile(error.to_string()))?;
+    let mut nodes = HashMap::new();
+    for node in database.root.children {
+        if let Node::Group(mut container) = node {
+            let path = serde_json::from_str(&container.name)
+                .map_err(|error| OpenError::CorruptFile(error.to_string()))?;
+            for_each_entry(&mut container, &mut |entry| decode_binary_fields(entry))
+                .map_err(OpenError::CorruptFile)?;
+            nodes.insert(path, container);
+        }
+    }
+    Ok(Changeset { changes, nodes })
+}
+
+/// Calls `f` for all entries in the group, in its sub groups and in the history of entries
+fn for_each_entry<E>(
+    group: &mut Group,
+    f: &mut dyn FnMut(&mut Entry) -> Result<(), E>,
+) -> Result<(), E> {
+    for node in &mut group.children {
+        match node {
+            Node::Group(group) => for_each_entry(group, f)?,
+            Node::Entry(entry) => {
+                if let Some(history) = entry.history.take() {
+                    // history entries can only be added, newest first
+                    let mut changed = History::default();
+                    for mut old in history.get_entries().iter().rev().cloned() {
+                        f(&mut old)?;
+                        changed.add_entry(old);
+                    }
+                    entry.history = Some(changed);
+                }
+                f(entry)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn encode_binary_fields(entry: &mut Entry) -> Result<(), std::io::Error> {
+    let mut names = Vec::new();
+    for (name, value) in &mut entry.fields {
+        if let Value::Bytes(bytes) = value {
+            *value = Value::Unprotected(general_purpose::STANDARD.encode(bytes));
+            names.push(name.clone());
+        }
+    }
+    if !names.is_empty() {
+        let item = CustomDataItem {
+            value: Some(Value::Unprotected(serde_json::to_string(&names)?)),
+            last_modification_time: None,
+        };
+        entry
+            .custom_data
+            .items
+            .insert(BINARY_FIELDS.to_owned(), item);
+    }
+    Ok(())
+}
+
+fn decode_binary_fields(entry: &mut Entry) -> Result<(), String> {
+    let names: Vec<String> = match entry.custom_data.items.remove(BINARY_FIELDS) {
+        Some(CustomDataItem {
+            value: Some(Value::Unprotected(json)),
+            ..
+        }) => serde_json::from_str(&json).map_err(|error| error.to_string())?,
+        Some(_) => return Err("invalid binary fields".to_owned()),
+        None => return Ok(()),
+    };
+    for name in names {
+        let value = match entry.fields.get(&name) {
+            Some(Value::Unprotected(encoded)) => general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|error| error.to_string())?,
+            _ => return Err(format!("invalid binary field {}", name)),
+        };
+        entry.fields.insert(name, Value::Bytes(value));
+    }
+    Ok(())
+}
+
+/// Applies the changeset to the database, which has to look like database A of the differences
+/// did. Nothing is changed if any part of the database does not match, all mismatches are
+/// returned instead. Changed entries keep their previous version in their history.
+pub fn apply(database: &mut Database, changeset: &Changeset) -> Result<(), Vec<Mismatch>> {
+    let mut root = database.root.clone();
+    let mut mismatches = Vec::new();
+    let path = [changeset.changes.name.clone()];
+    apply_group(
+        &mut root,
+        &changeset.changes,
+        changeset,
+        &path,
+        &mut mismatches,
+    );
+    if mismatches.is_empty() {
+        database.root = root;
+        Ok(())
+    } else {
+        Err(mismatches)
+    }
+}
+
+fn apply_group(
+    group: &mut Group,
+    diff: &GroupDiff,
+    changeset: &Changeset,
+    path: &[String],
+    mismatches: &mut Vec<Mismatch>,
+) {
+    let mut modified = Vec::new();
+    for change in &diff.groups {
+        match change {
+            // added groups and entries are only the same as existing ones if their UUID is
+            GroupChange::Added(info) => match changeset.group(path, &info.name) {
+                None => mismatches.push(mismatch(
+                    path,
+                    &info.name,
+                    "group is missing in the changeset",
+                )),
+                Some(added) if group.children.iter().any(|node| is_same_group(node, added)) => {
+                    mismatches.push(mismatch(path, &info.name, "group already exists"))
+                }
+                Some(added) => group.children.push(Node::Group(added.clone())),
+            },
+            GroupChange::Removed(info) => {
+                match group
+                    .children
+                    .iter()
+                    .position(|node| is_group(node, &info.name))
+                {
+                    None => mismatches.push(mismatch(path, &info.name, "group does not exist")),
+                    Some(index) => match &group.children[index] {
+                        Node::Group(child) if group::Group::from_keepass(child).info() != *info => {
+                            mismatches.push(mismatch(path, &info.name, "group was changed"))
+                        }
+                        _ => {
+                            group.children.remove(index);
+                        }
+                    },
+                }
+            }
+            GroupChange::Modified(diff) => {
+                if group.children.iter().any(|node| is_group(node, &diff.name)) {
+                    modified.push(diff);
+                } else {
+                    mismatches.push(mismatch(path, &diff.name, "group does not exist"));
+                }
+            }
+        }
+    }
+    for change in &diff.entries {
+        match change {
+            EntryChange::Added(info) => match changeset.entry(path, &info.uuid, &info.title) {
+                None => mismatches.push(mismatch(
+                    path,
+                    &info.title,
+                    "entry is missing in the changeset",
+                )),
+                Some(added)
+                    if group
+                        .children
+                        .iter()
+                        .any(|node| is_entry(node, &added.uuid.to_string())) =>
+                {
+                    mismatches.push(mismatch(path, &info.title, "entry already exists"))
+                }
+                Some(added) => group.add_child(added.clone()),
+            },
+            EntryChange::Removed(info) => {
+                let uuid = entry_in(group, &info.uuid, &info.title).map(|entry| {
+                    let current = entry::Entry::from_keepass(entry).info();
+                    (
+                        entry.uuid,
+                        current.title == info.title && current.fields == info.fields,
+                    )
+                });
+                match uuid {
+                    None => mismatches.push(mismatch(path, &info.title, "entry does not exist")),
+                    Some((_, false)) => {
+                        mismatches.push(mismatch(path, &info.title, "entry was changed"))
+                    }
+                    Some((uuid, true)) => group.children.retain(|node| match node {
+                        Node::Entry(entry) => entry.uuid != uuid,
+                        Node::Group(_) => true,
+                    }),
+                }
+            }
+            EntryChange::Modified(diff) => {
+                let new = changeset.entry(path, &diff.uuid, &diff.title);
+                match (entry_in_mut(group, &diff.uuid, &diff.title), new) {
+                    (None, _) => {
+                        mismatches.push(mismatch(path, &diff.title, "entry does not exist"))
+                    }
+                    (Some(_), None) => mismatches.push(mismatch(
+                        path,
+                        &diff.title,
+                        "entry is missing in the changeset",
+                    )),
+                    (Some(entry), Some(new)) => {
+                        apply_entry(entry, diff, new, &extend(path, &diff.title), mismatches)
+                    }
+                }
+            }
+        }
+    }
+    for diff in modified {
+        if let Some(Node::Group(child)) = group
+            .children
+            .iter_mut()
+            .find(|node| is_group(node, &diff.name))
+        {
+            apply_group(
+                child,
+                diff,
+                changeset,
+                &extend(path, &diff.name),
+                mismatches,
+            );
+        }
+    }
+}
+
+/// Applies the changed fields of `diff`, taking their values from `new`, the entry in B
+fn apply_entry(
+    entry: &mut Entry,
+    diff: &EntryDiff,
+    new: &Entry,
+    path: &[String],
+    mismatches: &mut Vec<Mismatch>,
+) {
+    let current = |name: &str| {
+        entry
+            .fields
+            .get(name)
+            .map(|value| field::Field::from_keepass(name, value).value)
+    };
+    let found: Vec<_> = diff
+        .fields
+        .iter()
+        .filter_map(|change| {
+            let reason = match (change, current(change.name())) {
+                (FieldChange::Added(_), Some(_)) => "field already exists",
+                (FieldChange::Removed(_), None) | (FieldChange::Changed { .. }, None) => {
+                    "field does not exist"
+                }
+                (FieldChange::Removed(field), Some(value)) if field.value != value => {
+                    "field was changed"
+                }
+                (FieldChange::Changed { old, .. }, Some(value)) if *old != value => {
+                    "field was changed"
+                }
+                (FieldChange::Added(_), None) | (FieldChange::Changed { .. }, Some(_))
+                    if !new.fields.contains_key(change.name()) =>
+                {
+                    "field is missing in the changeset"
+                }
+                _ => return None,
+            };
+            Some(mismatch(path, change.name(), reason))
+        })
+        .collect();
+    if !found.is_empty() {
+        mismatches.extend(found);
+        return;
+    }
+
+    let previous = entry.clone();
+    entry
+        .history
+        .get_or_insert_with(Default::default)
+        .add_entry(previous);
+    entry.times.set_last_modification(Times::now());
+    for change in &diff.fields {
+        let name = change.name();
+        match (change, new.fields.get(name)) {
+            (FieldChange::Removed(_), _) => {
+                entry.fields.remove(name);
+            }
+            (_, Some(value)) => {
+                entry.fields.insert(name.to_owned(), value.clone());
+            }
+            (_, None) => {}
+        }
+    }
+}
+
+fn is_same_group(node: &Node, group: &Group) -> bool {
+    matches!(node, Node::Group(child) if child.uuid == group.uuid)
+}
+
+fn mismatch(path: &[String], name: &str, reason: &str) -> Mismatch {
+    Mismatch {
+        path: extend(path, name),
+        reason: reason.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::entry;
+
+    fn database(entries: Vec<Entry>) -> Database {
+        let mut database = Database::new(Default::default());
+        for entry in entries {
+            database.root.add_child(entry);
+        }
+        database
+    }
+
+    fn changes(a: &Database, b: &Database) -> GroupDiff {
+        group::Group::from_keepass(&a.root).changes(&group::Group::from_keepass(&b.root))
+    }
+
+    fn changeset(a: &Database, b: &Database) -> Changeset {
+        Changeset::new(&changes(a, b), &b.root)
+    }
+
+    #[test]
+    fn turns_a_into_b() {
+        let mail = entry("Mail", "old");
+        let a = database(vec![mail.clone(), entry("Old", "x")]);
+        let mut changed = mail.clone();
+        changed.fields.insert(
+            "Password".to_owned(),
+            Value::Protected("new".as_bytes().into()),
+        );
+        let b = database(vec![changed, entry("New", "y")]);
+        let mut target = database(vec![mail, entry("Old", "x")]);
+
+        apply(&mut target, &changeset(&a, &b)).unwrap();
+
+        assert!(changes(&target, &b).is_identical());
+    }
+
+    #[test]
+    fn adds_entries_that_share_a_title_with_an_existing_one() {
+        let work = entry("Login", "work");
+        let a = database(vec![work.clone()]);
+        let b = database(vec![work.clone(), entry("Login", "home")]);
+        let mut target = database(vec![work]);
+
+        apply(&mut target, &changeset(&a, &b)).unwrap();
+
+        assert_eq!(target.root.children.len(), 2);
+        assert!(changes(&target, &b).is_identical());
+    }
+
+    #[test]
+    fn keeps_binary_values_and_nodes_through_a_changeset_file() {
+        let mail = entry("Mail", "old");
+        let a = database(vec![mail.clone()]);
+        let mut changed = mail.clone();
+        changed
+            .fields
+            .insert("Key".to_owned(), Value::Bytes(vec![0xff, 0x00, 0x9f]));
+        changed.fields.insert(
+            "Password".to_owned(),
+            Value::Protected("pässwört\nzwei".as_bytes().into()),
+        );
+        let mut added = entry("Added", "secret");
+        added
+            .fields
+            .insert("Attachment".to_owned(), Value::Bytes(vec![1, 2, 3]));
+        added
+            .history
+            .get_or_insert_with(Default::default)
+            .add_entry(entry("Added", "before"));
+        let mut group = Group::new("New group");
+        group.icon_id = Some(42);
+        group.add_child(entry("Inside", "deep"));
+        let mut b = database(vec![changed.clone(), added.clone()]);
+        b.root.add_child(group.clone());
+
+        let mut file = Vec::new();
+        let key = || DatabaseKey::new().with_password("changes");
+        save(&changeset(&a, &b), &mut file, key()).unwrap();
+        let loaded = load(&mut file.as_slice(), key()).unwrap();
+        let mut target = database(vec![mail]);
+        apply(&mut target, &loaded).unwrap();
+
+        assert!(changes(&target, &b).is_identical());
+        let find_entry = |uuid| {
+            target.root.children.iter().find_map(|node| match node {
+                Node::Entry(entry) if entry.uuid == uuid => Some(entry),
+                _ => None,
+            })
+        };
+        assert_eq!(changed.fields, find_entry(changed.uuid).unwrap().fields);
+        assert_eq!(Some(&added), find_entry(added.uuid));
+        assert!(target.root.children.contains(&Node::Group(group)));
+    }
+
+    #[test]
+    fn reports_a_different_pre_image() {
+        let a = database(vec![entry("Mail", "old")]);
+        let b = database(vec![entry("Mail", "new")]);
+        let mut target = database(vec![entry("Mail", "other")]);
+
+        let mismatches = apply(&mut target, &changeset(&a, &b)).unwrap_err();
+
+        assert_eq!(
+            vec![Mismatch {
+                path: vec!["Root".to_owned(), "Mail".to_owned(), "Password".to_owned()],
+                reason: "field was changed".to_owned(),
+            }],
+            mismatches
+        );
+        assert_eq!(Some("other"), entry_password(&target));
+    }
+
+    fn entry_password(database: &Database) -> Option<&str> {
+        database.root.children.iter().find_map(|node| match node {
+            Node::Entry(entry) => entry.get_password(),
+            Node::Group(_) => None,
+        })
+    }
+}
@@ -0,0 +1,343 @@
+use serde::{Deserialize, Serialize};
+
+use crate::diff::Kind;
+
+/// What protected values are replaced with in masked differences
+pub const MASK: &str = "***";
+
+/// A group that only exists in one of the databases, with everything in it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupInfo {
+    pub name: String,
+    pub groups: Vec<GroupInfo>,
+    pub entries: Vec<EntryInfo>,
+}
+
+/// An entry that only exists in one of the databases, with all its fields
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryInfo {
+    pub title: String,
+    pub uuid: String,
+    pub fields: Vec<FieldInfo>,
+}
+
+/// A field that only exists in one of the entries
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldInfo {
+    pub name: String,
+    pub value: String,
+    /// Whether the value is stored protected in the database, like passwords
+    pub protected: bool,
+}
+
+/// The differences between two groups with the same name. Groups and entries without
+/// differences are left out.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupDiff {
+    pub name: String,
+    pub groups: Vec<GroupChange>,
+    pub entries: Vec<EntryChange>,
+}
+
+/// The differences between two entries with the same title. Fields without differences are
+/// left out.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryDiff {
+    pub title: String,
+    /// The UUID of the entry in the first database
+    pub uuid: String,
+    pub fields: Vec<FieldChange>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum GroupChange {
+    Added(GroupInfo),
+    Removed(GroupInfo),
+    Modified(GroupDiff),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum EntryChange {
+    Added(EntryInfo),
+    Removed(EntryInfo),
+    Modified(EntryDiff),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum FieldChange {
+    Added(FieldInfo),
+    Removed(FieldInfo),
+    Changed {
+        name: String,
+        old: String,
+        new: String,
+        protected: bool,
+    },
+}
+
+/// A change of any kind, as found in the inner differences of a DiffResult
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    Group(GroupChange),
+    Entry(EntryChange),
+    Field(FieldChange),
+}
+
+impl GroupDiff {
+    /// A GroupDiff for two groups that are identical
+    pub fn identical(name: String) -> Self {
+        GroupDiff {
+            name,
+            groups: Vec::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Collects the changes of the children of a group. Changes of fields are ignored, as
+    /// groups cannot contain fields.
+    pub fn from_changes<I: IntoIterator<Item = Change>>(name: String, changes: I) -> Self {
+        let mut diff = GroupDiff::identical(name);
+        for change in changes {
+            match change {
+                Change::Group(group) => diff.groups.push(group),
+                Change::Entry(entry) => diff.entries.push(entry),
+                Change::Field(_) => {}
+            }
+        }
+        diff
+    }
+
+    pub fn is_identical(&self) -> bool {
+        self.groups.is_empty() && self.entries.is_empty()
+    }
+
+    /// A copy of the differences with all protected values replaced by [`MASK`]. The
+    /// differences themselves always keep the values, so they can be rendered masked and
+    /// unmasked.
+    pub fn masked(&self) -> GroupDiff {
+        GroupDiff {
+            name: self.name.clone(),
+            groups: self.groups.iter().map(GroupChange::masked).collect(),
+            entries: self.entries.iter().map(EntryChange::masked).collect(),
+        }
+    }
+
+    pub fn label(&self, use_verbose: bool) -> String {
+        label(Kind::Group, &self.name, None, use_verbose)
+    }
+}
+
+impl EntryDiff {
+    pub fn label(&self, use_verbose: bool) -> String {
+        label(Kind::Entry, &self.title, None, use_verbose)
+    }
+
+    fn masked(&self) -> EntryDiff {
+        EntryDiff {
+            fields: self.fields.iter().map(FieldChange::masked).collect(),
+            ..self.clone()
+        }
+    }
+}
+
+impl GroupInfo {
+    pub fn label(&self, use_verbose: bool) -> String {
+        label(Kind::Group, &self.name, None, use_verbose)
+    }
+
+    fn masked(&self) -> GroupInfo {
+        GroupInfo {
+            name: self.name.clone(),
+            groups: self.groups.iter().map(GroupInfo::masked).collect(),
+            entries: self.entries.iter().map(EntryInfo::masked).collect(),
+        }
+    }
+}
+
+impl EntryInfo {
+    pub fn label(&self, use_verbose: bool) -> String {
+        label(Kind::Entry, &self.title, None, use_verbose)
+    }
+
+    fn masked(&self) -> EntryInfo {
+        EntryInfo {
+            fields: self.fields.iter().map(FieldInfo::masked).collect(),
+            ..self.clone()
+        }
+    }
+}
+
+impl FieldInfo {
+    pub fn label(&self, use_verbose: bool) -> String {
+        label(Kind::Field, &self.name, Some(&self.value), use_verbose)
+    }
+
+    fn masked(&self) -> FieldInfo {
+        FieldInfo {
+            value: mask(&self.value, self.protected),
+            ..self.clone()
+        }
+    }
+}
+
+impl GroupChange {
+    pub fn name(&self) -> &str {
+        match self {
+            GroupChange::Added(group) | GroupChange::Removed(group) => &group.name,
+            GroupChange::Modified(diff) => &diff.name,
+        }
+    }
+
+    fn masked(&self) -> GroupChange {
+        match self {
+            GroupChange::Added(group) => GroupChange::Added(group.masked()),
+            GroupChange::Removed(group) => GroupChange::Removed(group.masked()),
+            GroupChange::Modified(diff) => GroupChange::Modified(diff.masked()),
+        }
+    }
+}
+
+impl EntryChange {
+    pub fn title(&self) -> &str {
+        match self {
+            EntryChange::Added(entry) | EntryChange::Removed(entry) => &entry.title,
+            EntryChange::Modified(diff) => &diff.title,
+        }
+    }
+
+    pub fn uuid(&self) -> &str {
+        match self {
+            EntryChange::Added(entry) | EntryChange::Removed(entry) => &entry.uuid,
+            EntryChange::Modified(diff) => &diff.uuid,
+        }
+    }
+
+    fn masked(&self) -> EntryChange {
+        match self {
+            EntryChange::Added(entry) => EntryChange::Added(entry.masked()),
+            EntryChange::Removed(entry) => EntryChange::Removed(entry.masked()),
+            EntryChange::Modified(diff) => EntryChange::Modified(diff.masked()),
+        }
+    }
+}
+
+impl FieldChange {
+    pub fn name(&self) -> &str {
+        match self {
+            FieldChange::Added(field) | FieldChange::Removed(field) => &field.name,
+            FieldChange::Changed { name, .. } => name,
+        }
+    }
+
+    fn masked(&self) -> FieldChange {
+        match self {
+            FieldChange::Added(field) => FieldChange::Added(field.masked()),
+            FieldChange::Removed(field) => FieldChange::Removed(field.masked()),
+            FieldChange::Changed {
+                name,
+                old,
+                new,
+                protected,
+            } => FieldChange::Changed {
+                name: name.clone(),
+                old: mask(old, *protected),
+                new: mask(new, *protected),
+                protected: *protected,
+            },
+        }
+    }
+}
+
+impl Change {
+    /// A copy of the change with all protected values replaced by [`MASK`]
+    pub fn masked(&self) -> Change {
+        match self {
+            Change::Group(group) => Change::Group(group.masked()),
+            Change::Entry(entry) => Change::Entry(entry.masked()),
+            Change::Field(field) => Change::Field(field.masked()),
+        }
+    }
+}
+
+fn mask(value: &str, protected: bool) -> String {
+    if protected {
+        MASK.to_owned()
+    } else {
+        value.to_owned()
+    }
+}
+
+/// How a group, entry or field is shown in the text output, like `Entry 'Mail'` in verbose
+/// output or `Password = secret` for a field
+pub fn label(kind: Kind, name: &str, value: Option<&str>, use_verbose: bool) -> String {
+    match (kind, use_verbose) {
+        (Kind::Group, true) => format!("Group '{}'", name),
+        (Kind::Entry, true) => format!("Entry '{}'", name),
+        (Kind::Field, true) => format!("Field '{}' = '{}'", name, value.unwrap_or_default()),
+        (Kind::Field, false) => format!("{} = {}", name, value.unwrap_or_default()),
+        (_, false) => name.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn serializes_changes_with_tags() {
+        let diff = GroupDiff {
+            name: "Root".to_owned(),
+            groups: vec![],
+            entries: vec![EntryChange::Modified(EntryDiff {
+                title: "Mail".to_owned(),
+                uuid: "1234".to_owned(),
+                fields: vec![FieldChange::Changed {
+                    name: "Password".to_owned(),
+                    old: "old".to_owned(),
+                    new: "new".to_owned(),
+                    protected: true,
+                }],
+            })],
+        };
+
+        let json = serde_json::to_string(&diff).unwrap();
+        assert_eq!(
+            r#"{"name":"Root","groups":[],"entries":[{"change":"modified","title":"Mail","uuid":"1234","fields":[{"change":"changed","name":"Password","old":"old","new":"new","protected":true}]}]}"#,
+            json
+        );
+        assert_eq!(diff, serde_json::from_str(&json).unwrap());
+    }
+
+    #[test]
+    fn masks_only_protected_values() {
+        let field = |name: &str, protected| FieldInfo {
+            name: name.to_owned(),
+            value: "secret".to_owned(),
+            protected,
+        };
+        let diff = GroupDiff {
+            name: "Root".to_owned(),
+            groups: vec![],
+            entries: vec![EntryChange::Added(EntryInfo {
+                title: "Mail".to_owned(),
+                uuid: "1234".to_owned(),
+                fields: vec![field("Password", true), field("UserName", false)],
+            })],
+        };
+
+        let masked = diff.masked();
+
+        match &masked.entries[0] {
+            EntryChange::Added(entry) => {
+                assert_eq!("***", entry.fields[0].value);
+                assert_eq!("secret", entry.fields[1].value);
+            }
+            change => panic!("unexpected change {:?}", change),
+        }
+        assert_ne!(diff, masked);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::diff::changes::GroupDiff;
+use crate::diff::path::PathStyle;
+use crate::diff::record::{self, Record};
+
+/// Helper wrapper to impl Display for a CSV table of all differences
+pub struct CsvDisplay<'a> {
+    pub changes: &'a GroupDiff,
+}
+
+impl<'a> std::fmt::Display for CsvDisplay<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let records = record::from_changes(self.changes);
+
+        writeln!(f, "change,group,title,uuid,field,old,new")?;
+        for record in records {
+            format_record(&record, f)?;
+        }
+        Ok(())
+    }
+}
+
+fn format_record(record: &Record, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let empty = String::new();
+    writeln!(
+        f,
+        "{},{},{},{},{},{},{}",
+        record.change,
+        quote(&record.location.group_path(PathStyle::Slashes)),
+        quote(record.location.title.as_ref().unwrap_or(&empty)),
+        quote(record.location.uuid.as_ref().unwrap_or(&empty)),
+        quote(record.field.as_ref().unwrap_or(&empty)),
+        quote(record.old.as_ref().unwrap_or(&empty)),
+        quote(record.new.as_ref().unwrap_or(&empty)),
+    )
+}
+
+/// Quotes a value as described in RFC 4180, if it contains separators, quotes or line breaks.
+/// Values that spreadsheets would take as a formula, like passwords starting with `=`, are
+/// prefixed with `'` to keep them text.
+pub fn quote(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_owned()
+    };
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn leaves_simple_values_alone() {
+        assert_eq!("Some group", quote("Some group"));
+    }
+
+    #[test]
+    fn quotes_separators_and_quotes() {
+        assert_eq!("\"a, b\"", quote("a, b"));
+        assert_eq!("\"say \"\"hi\"\"\"", quote("say \"hi\""));
+        assert_eq!("\"two\nlines\"", quote("two\nlines"));
+    }
+
+    #[test]
+    fn keeps_formulas_text() {
+        assert_eq!("\"'=HYPERLINK(\"\"x\"\")\"", quote("=HYPERLINK(\"x\")"));
+        assert_eq!("'+1", quote("+1"));
+        assert_eq!("'-secret", quote("-secret"));
+        assert_eq!("'@SUM(A1)", quote("@SUM(A1)"));
+        assert_eq!("a=b", quote("a=b"));
+    }
+}
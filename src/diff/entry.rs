@@ -1,53 +1,81 @@
-use base64::{engine::general_purpose, Engine as _};
-use keepass::db::Value;
 use std::collections::HashMap;
 
-use crate::diff::field::{Field, ValueType};
-use crate::diff::{Diff, DiffResult, DiffResultFormat};
+use crate::diff::changes::{Change, EntryChange, EntryDiff, EntryInfo, MASK};
+use crate::diff::field::Field;
+use crate::diff::path::escape;
+use crate::diff::{Diff, DiffElement, DiffResult, DiffResultFormat, Kind};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Entry {
+    pub uuid: String,
     pub fields: HashMap<String, Field>,
-    use_verbose: bool,
-    mask_passwords: bool,
 }
 
 impl Entry {
-    pub fn from_keepass(e: &keepass::db::Entry, use_verbose: bool, mask_passwords: bool) -> Self {
+    pub fn from_keepass(e: &keepass::db::Entry) -> Self {
         // username, password, etc. are just fields
         let fields = e
             .fields
             .iter()
-            .map(|(k, v)| {
-                (
-                    k.to_owned(),
-                    Field {
-                        name: k.to_owned(),
-                        value: match v {
-                            Value::Bytes(b) => general_purpose::STANDARD_NO_PAD.encode(b),
-                            Value::Unprotected(v) => v.to_owned(),
-                            Value::Protected(p) => String::from_utf8(p.unsecure().to_owned())
-                                .unwrap()
-                                .to_owned(),
-                        },
-                        kind: match v {
-                            Value::Bytes(_) => ValueType::Binary,
-                            Value::Unprotected(_) => ValueType::Unprotected,
-                            Value::Protected(_) => ValueType::Protected,
-                        },
-                        use_verbose,
-                        mask_passwords,
-                    },
-                )
-            })
+            .map(|(k, v)| (k.to_owned(), Field::from_keepass(k, v)))
             .collect();
 
         Entry {
+            uuid: e.uuid.to_string(),
             fields,
-            use_verbose,
-            mask_passwords,
         }
     }
+
+    /// The value of the Title field, or an empty String if there is none
+    pub fn title(&self) -> String {
+        self.fields
+            .get("Title")
+            .map(|field| field.value.clone())
+            .unwrap_or_default()
+    }
+
+    /// The entry with all its fields, sorted by name
+    pub fn info(&self) -> EntryInfo {
+        let mut fields: Vec<_> = self.fields.values().map(Field::info).collect();
+        fields.sort_by(|a, b| a.name.cmp(&b.name));
+        EntryInfo {
+            title: self.title(),
+            uuid: self.uuid.clone(),
+            fields,
+        }
+    }
+
+    /// Writes a stable text representation of the entry: a line with the group path and title,
+    /// followed by all fields sorted by name. Multi-line values are continued on aligned lines.
+    /// Protected values are replaced with `***` if `mask_passwords` is set.
+    pub fn write_canonical(
+        &self,
+        f: &mut dyn std::fmt::Write,
+        group_path: &str,
+        mask_passwords: bool,
+    ) -> std::fmt::Result {
+        writeln!(f, "{} {}", group_path, escape(&self.title(), &[]))?;
+        let mut names: Vec<_> = self.fields.keys().collect();
+        names.sort();
+        for name in names {
+            let field = &self.fields[name];
+            let value = if mask_passwords && field.is_protected() {
+                MASK
+            } else {
+                &field.value
+            };
+            let indent = " ".repeat(name.len() + 3);
+            for (index, line) in value.split('\n').enumerate() {
+                let line = line.trim_end_matches('\r');
+                if index == 0 {
+                    writeln!(f, "    {} = {}", name, line)?;
+                } else {
+                    writeln!(f, "    {}{}", indent, line)?;
+                }
+            }
+        }
+        writeln!(f)
+    }
 }
 
 impl Diff for Entry {
@@ -76,24 +104,41 @@ impl Diff for Entry {
     }
 }
 
-impl std::fmt::Display for Entry {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let name = self
-            .fields
-            .get("Title")
-            .unwrap_or(&Field {
-                name: "Title".to_string(),
-                value: "".to_string(),
-                kind: ValueType::Unprotected,
-                use_verbose: self.use_verbose,
-                mask_passwords: self.mask_passwords,
-            })
-            .value
-            .clone();
-        if self.use_verbose {
-            write!(f, "Entry '{}'", name)
-        } else {
-            write!(f, "{}", name)
-        }
+impl DiffElement for Entry {
+    fn kind(&self) -> Kind {
+        Kind::Entry
+    }
+
+    fn name(&self) -> String {
+        self.title()
+    }
+
+    fn uuid(&self) -> Option<String> {
+        Some(self.uuid.clone())
+    }
+
+    fn change(result: &DiffResult<'_, Self>) -> Option<Change> {
+        let change = match result {
+            // entries only change through their fields
+            DiffResult::Identical { .. } | DiffResult::Changed { .. } => return None,
+            DiffResult::InnerDifferences {
+                left,
+                inner_differences,
+                ..
+            } => EntryChange::Modified(EntryDiff {
+                title: left.title(),
+                uuid: left.uuid.clone(),
+                fields: inner_differences
+                    .iter()
+                    .filter_map(|id| match id.diff_result_change() {
+                        Some(Change::Field(field)) => Some(field),
+                        _ => None,
+                    })
+                    .collect(),
+            }),
+            DiffResult::OnlyLeft { left } => EntryChange::Removed(left.info()),
+            DiffResult::OnlyRight { right } => EntryChange::Added(right.info()),
+        };
+        Some(Change::Entry(change))
     }
 }
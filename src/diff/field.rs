@@ -1,4 +1,8 @@
-use crate::diff::{Diff, DiffResult};
+use base64::{engine::general_purpose, Engine as _};
+use keepass::db::Value;
+
+use crate::diff::changes::{Change, FieldChange, FieldInfo};
+use crate::diff::{Diff, DiffElement, DiffResult, Kind};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ValueType {
@@ -10,15 +14,57 @@ pub enum ValueType {
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Field {
     pub name: String,
+    /// The value as it is shown. Binary values are encoded as base64, protected values that are
+    /// not valid UTF-8 as hex.
     pub value: String,
+    /// The value as it is stored in the database, which is what gets compared
+    pub bytes: Vec<u8>,
     pub kind: ValueType,
-    pub use_verbose: bool,
-    pub mask_passwords: bool,
+}
+
+impl Field {
+    pub fn from_keepass(name: &str, value: &Value) -> Self {
+        let (value, bytes, kind) = match value {
+            Value::Bytes(b) => (
+                general_purpose::STANDARD_NO_PAD.encode(b),
+                b.clone(),
+                ValueType::Binary,
+            ),
+            Value::Unprotected(v) => (v.clone(), v.as_bytes().to_vec(), ValueType::Unprotected),
+            Value::Protected(p) => {
+                let bytes = p.unsecure().to_vec();
+                (text_of(&bytes), bytes, ValueType::Protected)
+            }
+        };
+        Field {
+            name: name.to_owned(),
+            value,
+            bytes,
+            kind,
+        }
+    }
+
+    /// Whether the value can be shown as text, which is only a problem for protected values
+    pub fn is_valid_utf8(&self) -> bool {
+        self.kind == ValueType::Binary || std::str::from_utf8(&self.bytes).is_ok()
+    }
+
+    pub fn is_protected(&self) -> bool {
+        self.kind == ValueType::Protected
+    }
+
+    pub fn info(&self) -> FieldInfo {
+        FieldInfo {
+            name: self.name.clone(),
+            value: self.value.clone(),
+            protected: self.is_protected(),
+        }
+    }
 }
 
 impl Diff for Field {
     fn diff<'a>(&'a self, other: &'a Self) -> DiffResult<'a, Self> {
-        if self.value == other.value {
+        if self.bytes == other.bytes {
             DiffResult::Identical {
                 left: self,
                 right: other,
@@ -32,28 +78,74 @@ impl Diff for Field {
     }
 }
 
-impl std::fmt::Display for Field {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        if self.use_verbose {
-            write!(
-                f,
-                "Field '{}' = '{}'",
-                self.name,
-                match (self.mask_passwords, self.kind) {
-                    (true, ValueType::Protected) => "***".to_owned(),
-                    _ => self.value.to_owned(),
-                }
-            )
-        } else {
-            write!(
-                f,
-                "{} = {}",
-                self.name,
-                match (self.mask_passwords, self.kind) {
-                    (true, ValueType::Protected) => "***".to_owned(),
-                    _ => self.value.to_owned(),
-                }
-            )
+impl DiffElement for Field {
+    fn kind(&self) -> Kind {
+        Kind::Field
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn value(&self) -> Option<String> {
+        Some(self.value.clone())
+    }
+
+    fn change(result: &DiffResult<'_, Self>) -> Option<Change> {
+        let change = match result {
+            // fields have no inner differences
+            DiffResult::Identical { .. } | DiffResult::InnerDifferences { .. } => return None,
+            DiffResult::Changed { left, right } => FieldChange::Changed {
+                name: left.name.clone(),
+                old: left.value.clone(),
+                new: right.value.clone(),
+                protected: left.is_protected() || right.is_protected(),
+            },
+            DiffResult::OnlyLeft { left } => FieldChange::Removed(left.info()),
+            DiffResult::OnlyRight { right } => FieldChange::Added(right.info()),
+        };
+        Some(Change::Field(change))
+    }
+}
+
+/// The bytes as text, or as hex like `0x6869ff` if they are not valid UTF-8
+fn text_of(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_owned(),
+        Err(_) => bytes
+            .iter()
+            .fold("0x".to_owned(), |hex, byte| hex + &format!("{:02x}", byte)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn protected(bytes: &[u8]) -> Field {
+        Field {
+            name: "Password".to_owned(),
+            value: text_of(bytes),
+            bytes: bytes.to_vec(),
+            kind: ValueType::Protected,
         }
     }
+
+    #[test]
+    fn shows_invalid_utf8_as_hex() {
+        let field = protected(&[0x68, 0x69, 0xff]);
+
+        assert!(!field.is_valid_utf8());
+        assert_eq!("0x6869ff", field.value);
+    }
+
+    #[test]
+    fn compares_the_bytes() {
+        let a = protected(&[0xff, 0x01]);
+        let b = protected(&[0xff, 0x02]);
+
+        assert!(matches!(a.diff(&a.clone()), DiffResult::Identical { .. }));
+        assert!(matches!(a.diff(&b), DiffResult::Changed { .. }));
+        assert!(protected(b"secret").is_valid_utf8());
+    }
 }
@@ -1,5 +1,8 @@
+use crate::diff::changes::{Change, GroupChange, GroupDiff, GroupInfo};
 use crate::diff::entry::Entry;
-use crate::diff::{Diff, DiffResult, DiffResultFormat};
+use crate::diff::path::{format_canonical, format_path, PathStyle};
+use crate::diff::{Diff, DiffElement, DiffResult, DiffResultFormat, Kind};
+use crate::stack::Stack;
 
 use std::collections::HashMap;
 
@@ -9,37 +12,30 @@ pub struct Group {
     name: String,
     child_groups: HashMap<String, Vec<Group>>,
     entries: HashMap<String, Vec<Entry>>,
-    use_verbose: bool,
 }
 
 impl Group {
     /// Create an entries list from a keepass::Group
-    pub fn from_keepass(
-        group: &keepass::db::Group,
-        use_verbose: bool,
-        mask_passwords: bool,
-    ) -> Self {
+    pub fn from_keepass(group: &keepass::db::Group) -> Self {
         let name = group.name.to_owned();
 
         let mut child_groups: HashMap<String, Vec<Group>> = HashMap::new();
         for node in group.children.iter() {
-            match node {
-                keepass::db::Node::Group(g) => child_groups
+            if let keepass::db::Node::Group(g) = node {
+                child_groups
                     .entry(g.name.clone())
-                    .or_insert(Vec::new())
-                    .push(Group::from_keepass(g, use_verbose, mask_passwords)),
-                _ => {}
+                    .or_default()
+                    .push(Group::from_keepass(g))
             }
         }
 
         let mut entries: HashMap<String, Vec<Entry>> = HashMap::new();
         for node in group.children.iter() {
-            match node {
-                keepass::db::Node::Entry(e) => entries
+            if let keepass::db::Node::Entry(e) = node {
+                entries
                     .entry(e.get("Title").unwrap_or_default().to_owned())
-                    .or_insert(Vec::new())
-                    .push(Entry::from_keepass(e, use_verbose, mask_passwords)),
-                _ => {}
+                    .or_default()
+                    .push(Entry::from_keepass(e))
             }
         }
 
@@ -47,17 +43,102 @@ impl Group {
             name,
             child_groups,
             entries,
-            use_verbose,
         }
     }
-}
 
-impl std::fmt::Display for Group {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.use_verbose {
-            write!(f, "Group '{}'", self.name)
-        } else {
-            write!(f, "{}", self.name)
+    /// Compares this group with `other` and collects the differences into an owned GroupDiff
+    pub fn changes(&self, other: &Group) -> GroupDiff {
+        match Group::change(&self.diff(other)) {
+            Some(Change::Group(GroupChange::Modified(diff))) => diff,
+            _ => GroupDiff::identical(self.name.clone()),
+        }
+    }
+
+    /// The group with all its sub groups and entries, sorted by name
+    pub fn info(&self) -> GroupInfo {
+        let mut groups: Vec<_> = self
+            .child_groups
+            .values()
+            .flatten()
+            .map(Group::info)
+            .collect();
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut entries: Vec<_> = self.entries.values().flatten().map(Entry::info).collect();
+        entries.sort_by(|a, b| a.title.cmp(&b.title));
+        GroupInfo {
+            name: self.name.clone(),
+            groups,
+            entries,
+        }
+    }
+
+    /// Writes a stable text representation of the group and everything in it. Every group gets a
+    /// line with its path, followed by one stanza per entry. Entries and sub groups are sorted, so
+    /// that the result only depends on the contents of the database.
+    pub fn write_canonical(
+        &self,
+        f: &mut dyn std::fmt::Write,
+        path: &Stack<&String>,
+        mask_passwords: bool,
+    ) -> std::fmt::Result {
+        let path = path.append(&self.name);
+        let group_path = format_canonical(&path.to_vec());
+        writeln!(f, "{}", group_path)?;
+        writeln!(f)?;
+
+        let mut titles: Vec<_> = self.entries.keys().collect();
+        titles.sort();
+        for title in titles {
+            for entry in &self.entries[title] {
+                entry.write_canonical(f, &group_path, mask_passwords)?;
+            }
+        }
+
+        let mut names: Vec<_> = self.child_groups.keys().collect();
+        names.sort();
+        for name in names {
+            for group in &self.child_groups[name] {
+                group.write_canonical(f, &path, mask_passwords)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Describes every protected value that is not valid UTF-8 and is therefore shown as hex
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        self.collect_warnings(&Stack::empty(), &mut warnings);
+        warnings
+    }
+
+    fn collect_warnings(&self, path: &Stack<&String>, warnings: &mut Vec<String>) {
+        let path = path.append(&self.name);
+        let group_path = format_path(&path.to_vec(), PathStyle::default());
+
+        let mut titles: Vec<_> = self.entries.keys().collect();
+        titles.sort();
+        for title in titles {
+            for entry in &self.entries[title] {
+                let mut names: Vec<_> = entry.fields.keys().collect();
+                names.sort();
+                for name in names
+                    .into_iter()
+                    .filter(|name| !entry.fields[*name].is_valid_utf8())
+                {
+                    warnings.push(format!(
+                        "Field '{}' of entry '{}' ({}) in {} is not valid UTF-8 and is shown as hex",
+                        name, title, entry.uuid, group_path
+                    ));
+                }
+            }
+        }
+
+        let mut names: Vec<_> = self.child_groups.keys().collect();
+        names.sort();
+        for name in names {
+            for group in &self.child_groups[name] {
+                group.collect_warnings(&path, warnings);
+            }
         }
     }
 }
@@ -95,3 +176,33 @@ impl Diff for Group {
         }
     }
 }
+
+impl DiffElement for Group {
+    fn kind(&self) -> Kind {
+        Kind::Group
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn change(result: &DiffResult<'_, Self>) -> Option<Change> {
+        let change = match result {
+            // groups only change through their children
+            DiffResult::Identical { .. } | DiffResult::Changed { .. } => return None,
+            DiffResult::InnerDifferences {
+                left,
+                inner_differences,
+                ..
+            } => GroupChange::Modified(GroupDiff::from_changes(
+                left.name.clone(),
+                inner_differences
+                    .iter()
+                    .filter_map(|id| id.diff_result_change()),
+            )),
+            DiffResult::OnlyLeft { left } => GroupChange::Removed(left.info()),
+            DiffResult::OnlyRight { right } => GroupChange::Added(right.info()),
+        };
+        Some(Change::Group(change))
+    }
+}
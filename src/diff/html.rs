@@ -0,0 +1,308 @@
+use crate::diff::changes::{
+    self, EntryChange, EntryDiff, FieldChange, GroupChange, GroupDiff, GroupInfo,
+};
+use crate::diff::summary::{Counts, Summary};
+use crate::diff::Kind;
+
+const STYLE: &str = r#"
+body { font-family: sans-serif; margin: 2em; color: #222; }
+h1 { font-size: 1.4em; }
+.files { font-family: monospace; }
+table.summary { border-collapse: collapse; margin: 1em 0; }
+table.summary th, table.summary td { border: 1px solid #ccc; padding: 0.2em 0.8em; text-align: right; }
+table.summary th:first-child, table.summary td:first-child { text-align: left; }
+ul.tree, ul.tree ul { list-style: none; padding-left: 1.5em; font-family: monospace; }
+ul.tree { padding-left: 0; }
+li { margin: 0.15em 0; }
+summary { cursor: pointer; }
+li.group > details > summary { font-weight: bold; }
+.added { background: #e6ffec; color: #116329; }
+.removed { background: #ffebe9; color: #82071e; }
+.changed > details > summary { background: #fff8c5; color: #4d2d00; }
+.controls button { margin-right: 0.5em; }
+"#;
+
+const SCRIPT: &str = r#"
+function setAllDetails(open) {
+  document.querySelectorAll("details").forEach(function (d) { d.open = open; });
+}
+"#;
+
+/// Helper wrapper to impl Display for a self-contained HTML report of the differences
+pub struct HtmlDisplay<'a> {
+    pub changes: &'a GroupDiff,
+    pub name_a: String,
+    pub name_b: String,
+    pub use_verbose: bool,
+}
+
+impl<'a> std::fmt::Display for HtmlDisplay<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let summary = Summary::from_changes(self.changes);
+
+        writeln!(f, "<!DOCTYPE html>")?;
+        writeln!(f, "<html lang=\"en\">")?;
+        writeln!(f, "<head>")?;
+        writeln!(f, "<meta charset=\"utf-8\">")?;
+        writeln!(
+            f,
+            "<title>keepass-diff: {} / {}</title>",
+            escape(&self.name_a),
+            escape(&self.name_b)
+        )?;
+        writeln!(f, "<style>{}</style>", STYLE)?;
+        writeln!(f, "<script>{}</script>", SCRIPT)?;
+        writeln!(f, "</head>")?;
+        writeln!(f, "<body>")?;
+        writeln!(f, "<header>")?;
+        writeln!(f, "<h1>keepass-diff</h1>")?;
+        writeln!(f, "<div class=\"files\">")?;
+        writeln!(f, "<div class=\"removed\">- {}</div>", escape(&self.name_a))?;
+        writeln!(f, "<div class=\"added\">+ {}</div>", escape(&self.name_b))?;
+        writeln!(f, "</div>")?;
+        format_summary(&summary, f)?;
+        writeln!(f, "</header>")?;
+        writeln!(f, "<main>")?;
+        if summary.is_empty() {
+            writeln!(f, "<p>No differences.</p>")?;
+        } else {
+            writeln!(f, "<p class=\"controls\">")?;
+            writeln!(
+                f,
+                "<button type=\"button\" onclick=\"setAllDetails(true)\">Expand all</button>"
+            )?;
+            writeln!(
+                f,
+                "<button type=\"button\" onclick=\"setAllDetails(false)\">Collapse all</button>"
+            )?;
+            writeln!(f, "</p>")?;
+            writeln!(f, "<ul class=\"tree\">")?;
+            self.format_group(f, self.changes)?;
+            writeln!(f, "</ul>")?;
+        }
+        writeln!(f, "</main>")?;
+        writeln!(f, "</body>")?;
+        writeln!(f, "</html>")
+    }
+}
+
+fn format_summary(summary: &Summary, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "<table class=\"summary\">")?;
+    writeln!(
+        f,
+        "<tr><th></th><th class=\"added\">Added</th><th class=\"removed\">Removed</th><th>Changed</th></tr>"
+    )?;
+    let rows: [(&str, &Counts); 3] = [
+        ("Groups", &summary.groups),
+        ("Entries", &summary.entries),
+        ("Fields", &summary.fields),
+    ];
+    for (name, counts) in rows {
+        writeln!(
+            f,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            name, counts.added, counts.removed, counts.changed
+        )?;
+    }
+    writeln!(f, "</table>")
+}
+
+impl<'a> HtmlDisplay<'a> {
+    /// Formats a changed group as a list item, recursing into its children
+    fn format_group(&self, f: &mut std::fmt::Formatter<'_>, diff: &GroupDiff) -> std::fmt::Result {
+        self.open_changed(f, Kind::Group, &diff.label(self.use_verbose))?;
+        for group in &diff.groups {
+            match group {
+                GroupChange::Added(group) => self.format_group_info(f, group, "added", '+')?,
+                GroupChange::Removed(group) => self.format_group_info(f, group, "removed", '-')?,
+                GroupChange::Modified(diff) => self.format_group(f, diff)?,
+            }
+        }
+        for entry in &diff.entries {
+            match entry {
+                EntryChange::Added(entry) => {
+                    format_added(f, Kind::Entry, &entry.label(self.use_verbose))?
+                }
+                EntryChange::Removed(entry) => {
+                    format_removed(f, Kind::Entry, &entry.label(self.use_verbose))?
+                }
+                EntryChange::Modified(diff) => self.format_entry(f, diff)?,
+            }
+        }
+        close_changed(f)
+    }
+
+    /// Formats an added or removed group as a collapsible list of everything inside of it, all
+    /// with the same class and sign
+    fn format_group_info(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        group: &GroupInfo,
+        class: &str,
+        sign: char,
+    ) -> std::fmt::Result {
+        let label = escape(&group.label(self.use_verbose));
+        if group.groups.is_empty() && group.entries.is_empty() {
+            return writeln!(
+                f,
+                "<li class=\"{} {}\">{} {}</li>",
+                class,
+                class_of(Kind::Group),
+                sign,
+                label
+            );
+        }
+        writeln!(f, "<li class=\"{} {}\">", class, class_of(Kind::Group))?;
+        writeln!(f, "<details open>")?;
+        writeln!(f, "<summary>{} {}</summary>", sign, label)?;
+        writeln!(f, "<ul>")?;
+        for child in &group.groups {
+            self.format_group_info(f, child, class, sign)?;
+        }
+        for entry in &group.entries {
+            writeln!(
+                f,
+                "<li class=\"{} {}\">{} {}</li>",
+                class,
+                class_of(Kind::Entry),
+                sign,
+                escape(&entry.label(self.use_verbose))
+            )?;
+        }
+        close_changed(f)
+    }
+
+    fn format_entry(&self, f: &mut std::fmt::Formatter<'_>, diff: &EntryDiff) -> std::fmt::Result {
+        self.open_changed(f, Kind::Entry, &diff.label(self.use_verbose))?;
+        for field in &diff.fields {
+            match field {
+                FieldChange::Added(field) => {
+                    format_added(f, Kind::Field, &field.label(self.use_verbose))?
+                }
+                FieldChange::Removed(field) => {
+                    format_removed(f, Kind::Field, &field.label(self.use_verbose))?
+                }
+                FieldChange::Changed { name, old, new, .. } => {
+                    let old = changes::label(Kind::Field, name, Some(old), self.use_verbose);
+                    let new = changes::label(Kind::Field, name, Some(new), self.use_verbose);
+                    writeln!(f, "<li class=\"changed {}\">", class_of(Kind::Field))?;
+                    writeln!(f, "<div class=\"removed\">- {}</div>", escape(&old))?;
+                    writeln!(f, "<div class=\"added\">+ {}</div>", escape(&new))?;
+                    writeln!(f, "</li>")?;
+                }
+            }
+        }
+        close_changed(f)
+    }
+
+    /// Opens a collapsible list item for a changed group or entry
+    fn open_changed(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        kind: Kind,
+        label: &str,
+    ) -> std::fmt::Result {
+        writeln!(f, "<li class=\"changed {}\">", class_of(kind))?;
+        writeln!(f, "<details open>")?;
+        writeln!(f, "<summary>~ {}</summary>", escape(label))?;
+        writeln!(f, "<ul>")
+    }
+}
+
+/// Closes the list item of a changed group or entry, or of an added or removed group
+fn close_changed(f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "</ul>")?;
+    writeln!(f, "</details>")?;
+    writeln!(f, "</li>")
+}
+
+fn format_added(f: &mut std::fmt::Formatter<'_>, kind: Kind, label: &str) -> std::fmt::Result {
+    writeln!(
+        f,
+        "<li class=\"added {}\">+ {}</li>",
+        class_of(kind),
+        escape(label)
+    )
+}
+
+fn format_removed(f: &mut std::fmt::Formatter<'_>, kind: Kind, label: &str) -> std::fmt::Result {
+    writeln!(
+        f,
+        "<li class=\"removed {}\">- {}</li>",
+        class_of(kind),
+        escape(label)
+    )
+}
+
+fn class_of(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Group => "group",
+        Kind::Entry => "entry",
+        Kind::Field => "field",
+    }
+}
+
+/// Escapes all characters with a special meaning in HTML text and attributes
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn escapes_special_characters() {
+        assert_eq!(
+            "&lt;b&gt;Tom &amp; &quot;Jerry&#39;s&quot;&lt;/b&gt;",
+            escape("<b>Tom & \"Jerry's\"</b>")
+        );
+    }
+
+    #[test]
+    fn keeps_regular_text() {
+        assert_eq!("plain text", escape("plain text"));
+    }
+
+    #[test]
+    fn lists_the_contents_of_added_groups() {
+        let entry = |title: &str| changes::EntryInfo {
+            title: title.to_owned(),
+            uuid: title.to_owned(),
+            fields: vec![],
+        };
+        let changes = GroupDiff {
+            name: "Root".to_owned(),
+            groups: vec![GroupChange::Added(GroupInfo {
+                name: "New".to_owned(),
+                groups: vec![],
+                entries: vec![entry("Mail"), entry("Bank")],
+            })],
+            entries: vec![],
+        };
+
+        let html = HtmlDisplay {
+            changes: &changes,
+            name_a: "a.kdbx".to_owned(),
+            name_b: "b.kdbx".to_owned(),
+            use_verbose: false,
+        }
+        .to_string();
+
+        assert!(html.contains("<tr><td>Entries</td><td>2</td><td>0</td><td>0</td></tr>"));
+        assert!(html.contains("<summary>+ New</summary>"));
+        assert!(html.contains("<li class=\"added entry\">+ Mail</li>"));
+        assert!(html.contains("<li class=\"added entry\">+ Bank</li>"));
+    }
+}
@@ -0,0 +1,237 @@
+use crate::diff::changes::{EntryChange, EntryDiff, FieldChange, GroupChange, GroupDiff};
+use crate::diff::summary::{Counts, Summary};
+use crate::diff::Kind;
+use crate::stack::Stack;
+
+/// Helper wrapper to impl Display for a Markdown report of the differences
+pub struct MarkdownDisplay<'a> {
+    pub changes: &'a GroupDiff,
+    pub name_a: String,
+    pub name_b: String,
+}
+
+impl<'a> std::fmt::Display for MarkdownDisplay<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let summary = Summary::from_changes(self.changes);
+
+        writeln!(f, "# keepass-diff")?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "Comparing {} with {}.",
+            code_span(&self.name_a),
+            code_span(&self.name_b)
+        )?;
+        writeln!(f)?;
+        writeln!(f, "## Summary")?;
+        writeln!(f)?;
+        if summary.is_empty() {
+            return writeln!(f, "No differences.");
+        }
+        writeln!(f, "| | Added | Removed | Changed |")?;
+        writeln!(f, "| --- | ---: | ---: | ---: |")?;
+        let rows: [(&str, &Counts); 3] = [
+            ("Groups", &summary.groups),
+            ("Entries", &summary.entries),
+            ("Fields", &summary.fields),
+        ];
+        for (name, counts) in rows {
+            writeln!(
+                f,
+                "| {} | {} | {} | {} |",
+                name, counts.added, counts.removed, counts.changed
+            )?;
+        }
+        writeln!(f)?;
+        format_group(self.changes, f, &Stack::empty())
+    }
+}
+
+/// Formats a changed group as Markdown. Groups become headings, changed entries become
+/// tables of their changed fields.
+fn format_group(
+    diff: &GroupDiff,
+    f: &mut std::fmt::Formatter<'_>,
+    path: &Stack<&String>,
+) -> std::fmt::Result {
+    let name = escape(&diff.name);
+    let path = path.append(&name);
+    writeln!(
+        f,
+        "{} {}",
+        "#".repeat((path.len() + 1).min(6)),
+        path.mk_string("", " / ", "")
+    )?;
+    writeln!(f)?;
+    // entries and added or removed groups first, so they don't end up below the heading of a sub
+    // group
+    for entry in &diff.entries {
+        match entry {
+            EntryChange::Added(entry) => format_added(f, Kind::Entry, &entry.title)?,
+            EntryChange::Removed(entry) => format_removed(f, Kind::Entry, &entry.title)?,
+            EntryChange::Modified(diff) => format_entry(diff, f)?,
+        }
+    }
+    for group in &diff.groups {
+        match group {
+            GroupChange::Added(group) => format_added(f, Kind::Group, &group.name)?,
+            GroupChange::Removed(group) => format_removed(f, Kind::Group, &group.name)?,
+            GroupChange::Modified(_) => {}
+        }
+    }
+    for group in &diff.groups {
+        if let GroupChange::Modified(diff) = group {
+            format_group(diff, f, &path)?;
+        }
+    }
+    Ok(())
+}
+
+fn format_entry(diff: &EntryDiff, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(
+        f,
+        "Changed {} **{}**:",
+        kind_name(Kind::Entry),
+        escape(&diff.title)
+    )?;
+    writeln!(f)?;
+    writeln!(f, "| Field | Old | New |")?;
+    writeln!(f, "| --- | --- | --- |")?;
+    for field in &diff.fields {
+        match field {
+            FieldChange::Added(field) => writeln!(
+                f,
+                "| {} | | {} |",
+                escape(&field.name),
+                escape(&field.value)
+            )?,
+            FieldChange::Removed(field) => writeln!(
+                f,
+                "| {} | {} | |",
+                escape(&field.name),
+                escape(&field.value)
+            )?,
+            FieldChange::Changed { name, old, new, .. } => writeln!(
+                f,
+                "| {} | {} | {} |",
+                escape(name),
+                escape(old),
+                escape(new)
+            )?,
+        }
+    }
+    writeln!(f)
+}
+
+fn format_added(f: &mut std::fmt::Formatter<'_>, kind: Kind, name: &str) -> std::fmt::Result {
+    writeln!(f, "- Added {} **{}**", kind_name(kind), escape(name))?;
+    writeln!(f)
+}
+
+fn format_removed(f: &mut std::fmt::Formatter<'_>, kind: Kind, name: &str) -> std::fmt::Result {
+    writeln!(f, "- Removed {} **{}**", kind_name(kind), escape(name))?;
+    writeln!(f)
+}
+
+fn kind_name(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Group => "group",
+        Kind::Entry => "entry",
+        Kind::Field => "field",
+    }
+}
+
+/// Escapes characters that Markdown would interpret as formatting, including table separators
+/// and line breaks
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '#' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\r' | '\n' => escaped.push_str("<br>"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Wraps the text in a code span, delimited by more backticks than the text contains in a row.
+/// The text is padded with spaces if it starts or ends with a backtick, which Markdown strips.
+pub fn code_span(s: &str) -> String {
+    let longest_run = s
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run + 1);
+    if s.starts_with('`') || s.ends_with('`') {
+        format!("{} {} {}", fence, s, fence)
+    } else {
+        format!("{}{}{}", fence, s, fence)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::diff::changes::{EntryInfo, GroupInfo};
+
+    #[test]
+    fn escapes_formatting_characters() {
+        assert_eq!("a \\| b \\*c\\* \\_d\\_", escape("a | b *c* _d_"));
+    }
+
+    #[test]
+    fn replaces_line_breaks() {
+        assert_eq!("one<br>two<br>three", escape("one\r\ntwo\nthree"));
+    }
+
+    #[test]
+    fn fences_code_spans_with_more_backticks() {
+        assert_eq!("`a.kdbx`", code_span("a.kdbx"));
+        assert_eq!("``a`b.kdbx``", code_span("a`b.kdbx"));
+        assert_eq!("``` ``a.kdbx ```", code_span("``a.kdbx"));
+    }
+
+    #[test]
+    fn lists_added_groups_before_the_headings_of_changed_groups() {
+        let changes = GroupDiff {
+            name: "Root".to_owned(),
+            groups: vec![
+                GroupChange::Modified(GroupDiff {
+                    name: "A".to_owned(),
+                    groups: vec![],
+                    entries: vec![EntryChange::Added(EntryInfo {
+                        title: "Mail".to_owned(),
+                        uuid: "1".to_owned(),
+                        fields: vec![],
+                    })],
+                }),
+                GroupChange::Added(GroupInfo {
+                    name: "B".to_owned(),
+                    groups: vec![],
+                    entries: vec![],
+                }),
+            ],
+            entries: vec![],
+        };
+
+        let markdown = MarkdownDisplay {
+            changes: &changes,
+            name_a: "a.kdbx".to_owned(),
+            name_b: "b.kdbx".to_owned(),
+        }
+        .to_string();
+
+        let tree = &markdown[markdown.find("## Root").unwrap()..];
+        assert_eq!(
+            "## Root\n\n- Added group **B**\n\n### Root / A\n\n- Added entry **Mail**\n\n",
+            tree
+        );
+    }
+}
@@ -1,11 +1,30 @@
 use std::collections::{HashMap, HashSet};
-use termcolor::Color;
+use termcolor::{Color, ColorSpec, WriteColor};
 
-use stack::Stack;
+use crate::stack::Stack;
 
+pub mod changes;
+pub mod csv;
 pub mod entry;
 pub mod field;
 pub mod group;
+pub mod html;
+pub mod markdown;
+pub mod path;
+pub mod record;
+pub mod side_by_side;
+pub mod stat;
+pub mod summary;
+pub mod template;
+pub mod theme;
+pub mod three_way;
+pub mod tree;
+pub mod unified;
+pub mod visit;
+
+use crate::diff::changes::{Change, EntryChange, EntryDiff, FieldChange, GroupChange, GroupDiff};
+use crate::diff::path::{format_path, PathStyle};
+use crate::diff::theme::Theme;
 
 /// The possible outcomes of diffing two objects against another
 #[derive(Debug)]
@@ -26,155 +45,221 @@ pub enum DiffResult<'a, T> {
     OnlyRight { right: &'a T },
 }
 
+/// The kinds of objects that show up in a DiffResult
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Group,
+    Entry,
+    Field,
+}
+
+/// Denotes an object that can be part of a DiffResult
+pub trait DiffElement: std::fmt::Debug {
+    fn kind(&self) -> Kind;
+
+    /// The name of a group, the title of an entry or the key of a field
+    fn name(&self) -> String;
+
+    /// The value, only available for fields
+    fn value(&self) -> Option<String> {
+        None
+    }
+
+    /// The UUID of the object in the database, only available for entries
+    fn uuid(&self) -> Option<String> {
+        None
+    }
+
+    /// Converts a DiffResult of this type into an owned Change, or None if nothing changed
+    fn change(result: &DiffResult<'_, Self>) -> Option<Change>
+    where
+        Self: Sized;
+}
+
 /// Denotes that an object can be diffed
 pub trait Diff
 where
     Self: Sized,
 {
+    /// Compares `self` as the left side with `other` as the right side
     fn diff<'a>(&'a self, other: &'a Self) -> DiffResult<'a, Self>;
 }
 
-/// Denotes that an object can be formatted as a DiffResult
+/// Denotes the result of diffing two objects, regardless of their type
 pub trait DiffResultFormat: std::fmt::Debug {
-    fn diff_result_format(
-        &self,
-        f: &mut std::fmt::Formatter<'_>,
-        path: &Stack<&String>,
-        use_color: bool,
-        use_verbose: bool,
-        mask_passwords: bool,
-    ) -> std::fmt::Result;
+    /// The kind of the objects that were diffed
+    fn diff_result_kind(&self) -> Kind;
+
+    /// Whether the objects that were diffed are identical
+    fn diff_result_is_identical(&self) -> bool;
+
+    /// Converts the DiffResult and its inner differences into an owned Change
+    fn diff_result_change(&self) -> Option<Change>;
 }
 
-/// Helper wrapper to impl Display for a DiffResult with user-specified settings
-pub struct DiffDisplay<'a, T: DiffResultFormat> {
-    pub inner: T,
-    pub path: Stack<&'a String>,
-    pub use_color: bool,
+impl<'a, E> DiffResultFormat for DiffResult<'a, E>
+where
+    E: DiffElement,
+{
+    fn diff_result_kind(&self) -> Kind {
+        match self {
+            DiffResult::Identical { left, .. }
+            | DiffResult::Changed { left, .. }
+            | DiffResult::InnerDifferences { left, .. }
+            | DiffResult::OnlyLeft { left } => left.kind(),
+            DiffResult::OnlyRight { right } => right.kind(),
+        }
+    }
+
+    fn diff_result_is_identical(&self) -> bool {
+        matches!(self, DiffResult::Identical { .. })
+    }
+
+    fn diff_result_change(&self) -> Option<Change> {
+        E::change(self)
+    }
+}
+
+/// Denotes output that is written to a terminal and may use colors
+pub trait WriteColored {
+    fn write_colored(&self, f: &mut dyn WriteColor) -> std::io::Result<()>;
+}
+
+/// Helper wrapper to write the differences as text, with user-specified settings
+pub struct DiffDisplay<'a> {
+    pub changes: &'a GroupDiff,
     pub use_verbose: bool,
-    pub mask_passwords: bool,
+    pub path_style: PathStyle,
+    pub theme: Theme,
 }
 
-impl<'a, T: DiffResultFormat> std::fmt::Display for DiffDisplay<'a, T> {
-    fn fmt(&self, mut f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let result = self.inner.diff_result_format(
-            &mut f,
-            &self.path,
-            self.use_color,
-            self.use_verbose,
-            self.mask_passwords,
-        );
-        if self.use_color {
-            crate::reset_color();
-        }
+impl<'a> WriteColored for DiffDisplay<'a> {
+    fn write_colored(&self, f: &mut dyn WriteColor) -> std::io::Result<()> {
+        let result = if self.changes.is_identical() {
+            Ok(())
+        } else {
+            self.write_group(f, self.changes, &Stack::empty())
+        };
+        f.reset()?;
         result
     }
 }
 
-/// Format functionality for deep recursion
-impl<'a, E> DiffResultFormat for DiffResult<'a, E>
-where
-    E: std::fmt::Display + std::fmt::Debug,
-{
-    fn diff_result_format(
+impl<'a> DiffDisplay<'a> {
+    fn write_group(
         &self,
-        mut f: &mut std::fmt::Formatter<'_>,
+        f: &mut dyn WriteColor,
+        diff: &GroupDiff,
         path: &Stack<&String>,
-        use_color: bool,
-        use_verbose: bool,
-        mask_passwords: bool,
-    ) -> std::fmt::Result {
-        let _ = match self {
-            DiffResult::Identical { .. } => Ok(()),
-            DiffResult::Changed { left, right } => {
-                if use_color {
-                    crate::set_fg(Some(Color::Red));
+    ) -> std::io::Result<()> {
+        let label = diff.label(self.use_verbose);
+        self.write_modified(f, &label, path)?;
+        let path = path.append(&label);
+        for group in &diff.groups {
+            match group {
+                GroupChange::Added(group) => {
+                    self.write_line(f, '+', &group.label(self.use_verbose), &path)?
                 }
-                if use_verbose {
-                    let indent = "  ".repeat(path.len());
-                    write!(f, "- {}{}\n", indent, left)?;
-                } else {
-                    write!(
-                        f,
-                        "- {}\n",
-                        path.append(&format!("{}", left)).mk_string("[", ", ", "]")
-                    )?;
-                }
-                if use_color {
-                    crate::set_fg(Some(Color::Green));
-                }
-                if use_verbose {
-                    let indent = "  ".repeat(path.len());
-                    write!(f, "+ {}{}\n", indent, right)
-                } else {
-                    write!(
-                        f,
-                        "+ {}\n",
-                        path.append(&format!("{}", right)).mk_string("[", ", ", "]")
-                    )
+                GroupChange::Removed(group) => {
+                    self.write_line(f, '-', &group.label(self.use_verbose), &path)?
                 }
+                GroupChange::Modified(diff) => self.write_group(f, diff, &path)?,
             }
-            DiffResult::InnerDifferences {
-                left,
-                inner_differences,
-                ..
-            } => {
-                if use_verbose {
-                    if use_color {
-                        crate::set_fg(Some(Color::Yellow));
-                    }
-                    let indent = "  ".repeat(path.len());
-                    write!(f, "~ {}{}\n", indent, left)?;
+        }
+        for entry in &diff.entries {
+            match entry {
+                EntryChange::Added(entry) => {
+                    self.write_line(f, '+', &entry.label(self.use_verbose), &path)?
                 }
-                for id in inner_differences {
-                    id.diff_result_format(
-                        &mut f,
-                        &path.append(&format!("{}", left)),
-                        use_color,
-                        use_verbose,
-                        mask_passwords,
-                    )?;
+                EntryChange::Removed(entry) => {
+                    self.write_line(f, '-', &entry.label(self.use_verbose), &path)?
                 }
-                Ok(())
+                EntryChange::Modified(diff) => self.write_entry(f, diff, &path)?,
             }
-            DiffResult::OnlyLeft { left } => {
-                if use_color {
-                    crate::set_fg(Some(Color::Red));
-                }
-                if use_verbose {
-                    let indent = "  ".repeat(path.len());
-                    write!(f, "- {}{}\n", indent, left)
-                } else {
-                    write!(
-                        f,
-                        "- {}\n",
-                        path.append(&format!("{}", left)).mk_string("[", ", ", "]")
-                    )
+        }
+        Ok(())
+    }
+
+    fn write_entry(
+        &self,
+        f: &mut dyn WriteColor,
+        diff: &EntryDiff,
+        path: &Stack<&String>,
+    ) -> std::io::Result<()> {
+        let label = diff.label(self.use_verbose);
+        self.write_modified(f, &label, path)?;
+        let path = path.append(&label);
+        for field in &diff.fields {
+            match field {
+                FieldChange::Added(field) => {
+                    self.write_line(f, '+', &field.label(self.use_verbose), &path)?
                 }
-            }
-            DiffResult::OnlyRight { right } => {
-                if use_color {
-                    crate::set_fg(Some(Color::Green));
+                FieldChange::Removed(field) => {
+                    self.write_line(f, '-', &field.label(self.use_verbose), &path)?
                 }
-                if use_verbose {
-                    let indent = "  ".repeat(path.len());
-                    write!(f, "+ {}{}\n", indent, right)
-                } else {
-                    write!(
-                        f,
-                        "+ {}\n",
-                        path.append(&format!("{}", right)).mk_string("[", ", ", "]")
-                    )
+                FieldChange::Changed { name, old, new, .. } => {
+                    let old = changes::label(Kind::Field, name, Some(old), self.use_verbose);
+                    let new = changes::label(Kind::Field, name, Some(new), self.use_verbose);
+                    self.write_line(f, '-', &old, &path)?;
+                    self.write_line(f, '+', &new, &path)?;
                 }
             }
-        };
+        }
+        Ok(())
+    }
 
+    /// Modified groups and entries only get a line of their own in verbose output
+    fn write_modified(
+        &self,
+        f: &mut dyn WriteColor,
+        label: &str,
+        path: &Stack<&String>,
+    ) -> std::io::Result<()> {
+        if self.use_verbose {
+            set_fg(f, self.theme.changed)?;
+            writeln!(f, "~ {}{}", "  ".repeat(path.len()), label)?;
+        }
         Ok(())
     }
+
+    fn write_line(
+        &self,
+        f: &mut dyn WriteColor,
+        marker: char,
+        label: &str,
+        path: &Stack<&String>,
+    ) -> std::io::Result<()> {
+        let color = match marker {
+            '+' => self.theme.added,
+            _ => self.theme.removed,
+        };
+        set_fg(f, color)?;
+        if self.use_verbose {
+            writeln!(f, "{} {}{}", marker, "  ".repeat(path.len()), label)
+        } else {
+            writeln!(f, "{} {}", marker, path_to(path, label, self.path_style))
+        }
+    }
+}
+
+/// Sets the foreground color for everything that is written to `f` afterwards. Without a color,
+/// the default color of the terminal is used.
+pub fn set_fg(f: &mut dyn WriteColor, color: Option<Color>) -> std::io::Result<()> {
+    match color {
+        Some(color) => f.set_color(ColorSpec::new().set_fg(Some(color))),
+        None => f.reset(),
+    }
+}
+
+/// The path to an element below `path`, as shown in the non-verbose text output
+fn path_to(path: &Stack<&String>, label: &str, style: PathStyle) -> String {
+    let label = label.to_owned();
+    let path = path.append(&label);
+    format_path(&path.to_vec(), style)
 }
 
 /// Compare to HashMaps of the same value type with each other, returning a bool indicating whether
-/// there are any differences and a Vec<DiffResult<A>> listing all differences
+/// there are any differences and a `Vec<DiffResult<A>>` listing all differences
 pub fn diff_entry<'a, A>(
     a: &'a HashMap<String, A>,
     b: &'a HashMap<String, A>,
@@ -255,7 +340,7 @@ where
         match (el_a, el_b) {
             // both a and b have the key
             (Some(v_a), Some(v_b)) => {
-                v_a.into_iter()
+                v_a.iter()
                     .enumerate()
                     .for_each(|(index, value_a)| match v_b.get(index) {
                         Some(value_b) => {
@@ -274,7 +359,7 @@ where
                 if v_a.len() < v_b.len() {
                     has_differences = true;
                     v_b[v_a.len()..]
-                        .into_iter()
+                        .iter()
                         .for_each(|value_b| acc.push(DiffResult::OnlyRight { right: value_b }));
                 }
             }
@@ -282,13 +367,13 @@ where
             // only a has the key
             (Some(v_a), None) => {
                 has_differences = true;
-                v_a.into_iter()
+                v_a.iter()
                     .for_each(|e| acc.push(DiffResult::OnlyLeft { left: e }));
             }
             // only b has the key
             (None, Some(v_b)) => {
                 has_differences = true;
-                v_b.into_iter()
+                v_b.iter()
                     .for_each(|e| acc.push(DiffResult::OnlyRight { right: e }));
             }
 
@@ -304,7 +389,7 @@ where
 mod test {
 
     use super::*;
-    use diff::group::Group;
+    use crate::diff::group::Group;
 
     #[test]
     fn diff_empty_groups() {
@@ -312,6 +397,6 @@ mod test {
         let b = HashMap::<String, Vec<Group>>::new();
         let (has_differences, _) = diff_hashmap(&a, &b);
 
-        assert_eq!(false, has_differences);
+        assert!(!has_differences);
     }
 }
@@ -0,0 +1,119 @@
+/// How the path to a difference is written
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathStyle {
+    /// `[Root, Group, Entry]`, segments that contain separators are quoted like `"A, B"`
+    #[default]
+    Brackets,
+    /// `Root/Group/Entry`, slashes in a segment are escaped like `A\/B`
+    Slashes,
+}
+
+/// Joins the names of groups, entries and fields into a path that can be split into the same
+/// segments again
+pub fn format_path<S: AsRef<str>>(segments: &[S], style: PathStyle) -> String {
+    match style {
+        PathStyle::Brackets => {
+            let segments: Vec<String> = segments
+                .iter()
+                .map(|segment| quote(segment.as_ref()))
+                .collect();
+            format!("[{}]", segments.join(", "))
+        }
+        PathStyle::Slashes => segments
+            .iter()
+            .map(|segment| escape(segment.as_ref(), &['/']))
+            .collect::<Vec<_>>()
+            .join("/"),
+    }
+}
+
+/// Joins the names of groups like `[Root/Group]` for the canonical text of a database. Slashes
+/// and closing brackets in a segment are escaped, so that different paths never give the same
+/// text.
+pub fn format_canonical<S: AsRef<str>>(segments: &[S]) -> String {
+    let segments: Vec<String> = segments
+        .iter()
+        .map(|segment| escape(segment.as_ref(), &['/', ']']))
+        .collect();
+    format!("[{}]", segments.join("/"))
+}
+
+/// Puts a segment in double quotes if it is empty, contains a separator or quote, or starts or
+/// ends with whitespace
+pub fn quote(segment: &str) -> String {
+    let needs_quotes = segment.is_empty()
+        || segment.trim() != segment
+        || segment
+            .chars()
+            .any(|c| matches!(c, ',' | '[' | ']' | '"' | '\\') || c.is_control());
+    if needs_quotes {
+        format!("\"{}\"", escape(segment, &['"']))
+    } else {
+        segment.to_owned()
+    }
+}
+
+/// Escapes backslashes, control characters and the given special characters with a backslash
+pub(crate) fn escape(segment: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(segment.len());
+    for c in segment.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\\' => escaped.push_str("\\\\"),
+            c if special.contains(&c) => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_control() => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keeps_simple_segments() {
+        assert_eq!(
+            "[Root, Some group, Password = line]",
+            format_path(
+                &["Root", "Some group", "Password = line"],
+                PathStyle::Brackets
+            )
+        );
+    }
+
+    #[test]
+    fn quotes_segments_with_separators() {
+        assert_eq!(
+            r#"[Root, "A, B", "say \"hi\"", "x]", ""]"#,
+            format_path(
+                &["Root", "A, B", "say \"hi\"", "x]", ""],
+                PathStyle::Brackets
+            )
+        );
+    }
+
+    #[test]
+    fn escapes_slashes() {
+        assert_eq!(
+            r"Root/A\/B/back\\slash/two\nlines",
+            format_path(
+                &["Root", "A/B", "back\\slash", "two\nlines"],
+                PathStyle::Slashes
+            )
+        );
+    }
+
+    #[test]
+    fn escapes_canonical_paths() {
+        assert_eq!(r"[Root/A\/B]", format_canonical(&["Root", "A/B"]));
+        assert_eq!("[Root/A/B]", format_canonical(&["Root", "A", "B"]));
+        assert_eq!(r"[Root/x\]\r\n]", format_canonical(&["Root", "x]\r\n"]));
+    }
+}
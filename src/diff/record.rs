@@ -0,0 +1,157 @@
+use crate::diff::changes::{EntryDiff, EntryInfo, FieldInfo, GroupDiff, GroupInfo};
+use crate::diff::path::{format_path, PathStyle};
+use crate::diff::visit::{walk, Visitor};
+
+/// The type of change a Record describes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Added => write!(f, "added"),
+            Change::Removed => write!(f, "removed"),
+            Change::Changed => write!(f, "changed"),
+        }
+    }
+}
+
+/// Where in the database a difference was found
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Location {
+    /// Names of the groups, starting at the root group
+    pub group_path: Vec<String>,
+    /// Title of the entry, if the difference is inside of an entry
+    pub title: Option<String>,
+    /// UUID of the entry, if the difference is inside of an entry
+    pub uuid: Option<String>,
+}
+
+impl Location {
+    /// The names of the groups, followed by the title of the entry, joined into a path
+    pub fn path(&self, style: PathStyle) -> String {
+        let mut segments = self.group_path.clone();
+        segments.extend(self.title.clone());
+        format_path(&segments, style)
+    }
+
+    /// The names of the groups joined into a path
+    pub fn group_path(&self, style: PathStyle) -> String {
+        format_path(&self.group_path, style)
+    }
+
+    fn of_group(path: &[&str]) -> Location {
+        Location {
+            group_path: path.iter().map(|name| name.to_string()).collect(),
+            title: None,
+            uuid: None,
+        }
+    }
+
+    /// The location of an entry, with the title of the entry at the end of `path`
+    fn of_entry(path: &[&str], uuid: &str) -> Location {
+        let (title, group_path) = path.split_last().expect("path of an entry");
+        Location {
+            title: Some(title.to_string()),
+            uuid: Some(uuid.to_owned()),
+            ..Location::of_group(group_path)
+        }
+    }
+}
+
+/// A single difference, flattened into a row. Added or removed groups and entries result in a
+/// single Record without field, old and new values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub change: Change,
+    pub location: Location,
+    pub field: Option<String>,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// All Records of the differences, in the order they show up in the text output
+pub fn from_changes(changes: &GroupDiff) -> Vec<Record> {
+    let mut collector = Collector::default();
+    walk(changes, &mut collector);
+    collector.records
+}
+
+/// Collects the Records while the differences are walked
+#[derive(Default)]
+struct Collector {
+    records: Vec<Record>,
+    /// UUID of the entry whose fields are visited
+    uuid: String,
+}
+
+impl Collector {
+    fn push_field(&mut self, change: Change, path: &[&str], old: Option<&str>, new: Option<&str>) {
+        let (field, entry_path) = path.split_last().expect("path of a field");
+        self.records.push(Record {
+            change,
+            location: Location::of_entry(entry_path, &self.uuid),
+            field: Some(field.to_string()),
+            old: old.map(str::to_owned),
+            new: new.map(str::to_owned),
+        });
+    }
+}
+
+impl Visitor for Collector {
+    fn group_added(&mut self, path: &[&str], _group: &GroupInfo) {
+        let location = Location::of_group(path);
+        self.records
+            .push(Record::without_field(Change::Added, location));
+    }
+
+    fn group_removed(&mut self, path: &[&str], _group: &GroupInfo) {
+        let location = Location::of_group(path);
+        self.records
+            .push(Record::without_field(Change::Removed, location));
+    }
+
+    fn enter_entry(&mut self, _path: &[&str], entry: &EntryDiff) {
+        self.uuid = entry.uuid.clone();
+    }
+
+    fn entry_added(&mut self, path: &[&str], entry: &EntryInfo) {
+        let location = Location::of_entry(path, &entry.uuid);
+        self.records
+            .push(Record::without_field(Change::Added, location));
+    }
+
+    fn entry_removed(&mut self, path: &[&str], entry: &EntryInfo) {
+        let location = Location::of_entry(path, &entry.uuid);
+        self.records
+            .push(Record::without_field(Change::Removed, location));
+    }
+
+    fn field_added(&mut self, path: &[&str], field: &FieldInfo) {
+        self.push_field(Change::Added, path, None, Some(&field.value));
+    }
+
+    fn field_removed(&mut self, path: &[&str], field: &FieldInfo) {
+        self.push_field(Change::Removed, path, Some(&field.value), None);
+    }
+
+    fn field_changed(&mut self, path: &[&str], old: &str, new: &str) {
+        self.push_field(Change::Changed, path, Some(old), Some(new));
+    }
+}
+
+impl Record {
+    fn without_field(change: Change, location: Location) -> Record {
+        Record {
+            change,
+            location,
+            field: None,
+            old: None,
+            new: None,
+        }
+    }
+}
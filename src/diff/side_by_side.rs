@@ -0,0 +1,250 @@
+use termcolor::{Color, WriteColor};
+
+use crate::diff::changes::{self, EntryChange, EntryDiff, FieldChange, GroupChange, GroupDiff};
+use crate::diff::theme::Theme;
+use crate::diff::{set_fg, Kind, WriteColored};
+
+/// Helper wrapper to write a two-column view of the differences, with database A on the
+/// left and database B on the right. The marker column in the middle follows the conventions of
+/// sdiff: `|` for changed, `<` for only left and `>` for only right.
+pub struct SideBySideDisplay<'a> {
+    pub changes: &'a GroupDiff,
+    pub name_a: String,
+    pub name_b: String,
+    /// Total width of a line, including the marker column in the middle
+    pub width: usize,
+    pub use_verbose: bool,
+    pub theme: Theme,
+}
+
+impl<'a> WriteColored for SideBySideDisplay<'a> {
+    fn write_colored(&self, f: &mut dyn WriteColor) -> std::io::Result<()> {
+        let column_width = column_width(self.width);
+        let header_colors = self.theme.metadata.map(|color| (Some(color), Some(color)));
+        write_row(
+            f,
+            &self.name_a,
+            ' ',
+            &self.name_b,
+            column_width,
+            header_colors,
+        )?;
+        write_row(
+            f,
+            &"-".repeat(column_width),
+            ' ',
+            &"-".repeat(column_width),
+            column_width,
+            header_colors,
+        )?;
+        let result = if self.changes.is_identical() {
+            Ok(())
+        } else {
+            self.write_group(f, self.changes, 0, column_width)
+        };
+        f.reset()?;
+        result
+    }
+}
+
+impl<'a> SideBySideDisplay<'a> {
+    fn write_group(
+        &self,
+        f: &mut dyn WriteColor,
+        diff: &GroupDiff,
+        depth: usize,
+        column_width: usize,
+    ) -> std::io::Result<()> {
+        let verbose = self.use_verbose;
+        self.write_modified(f, &diff.label(verbose), depth, column_width)?;
+        for group in &diff.groups {
+            match group {
+                GroupChange::Added(group) => {
+                    self.write_added(f, &group.label(verbose), depth + 1, column_width)?
+                }
+                GroupChange::Removed(group) => {
+                    self.write_removed(f, &group.label(verbose), depth + 1, column_width)?
+                }
+                GroupChange::Modified(diff) => {
+                    self.write_group(f, diff, depth + 1, column_width)?
+                }
+            }
+        }
+        for entry in &diff.entries {
+            match entry {
+                EntryChange::Added(entry) => {
+                    self.write_added(f, &entry.label(verbose), depth + 1, column_width)?
+                }
+                EntryChange::Removed(entry) => {
+                    self.write_removed(f, &entry.label(verbose), depth + 1, column_width)?
+                }
+                EntryChange::Modified(diff) => {
+                    self.write_entry(f, diff, depth + 1, column_width)?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_entry(
+        &self,
+        f: &mut dyn WriteColor,
+        diff: &EntryDiff,
+        depth: usize,
+        column_width: usize,
+    ) -> std::io::Result<()> {
+        let verbose = self.use_verbose;
+        self.write_modified(f, &diff.label(verbose), depth, column_width)?;
+        let indent = "  ".repeat(depth + 1);
+        for field in &diff.fields {
+            match field {
+                FieldChange::Added(field) => {
+                    self.write_added(f, &field.label(verbose), depth + 1, column_width)?
+                }
+                FieldChange::Removed(field) => {
+                    self.write_removed(f, &field.label(verbose), depth + 1, column_width)?
+                }
+                FieldChange::Changed { name, old, new, .. } => write_row(
+                    f,
+                    &format!(
+                        "{}{}",
+                        indent,
+                        changes::label(Kind::Field, name, Some(old), verbose)
+                    ),
+                    '|',
+                    &format!(
+                        "{}{}",
+                        indent,
+                        changes::label(Kind::Field, name, Some(new), verbose)
+                    ),
+                    column_width,
+                    Some((self.theme.removed, self.theme.added)),
+                )?,
+            }
+        }
+        Ok(())
+    }
+
+    fn write_modified(
+        &self,
+        f: &mut dyn WriteColor,
+        label: &str,
+        depth: usize,
+        column_width: usize,
+    ) -> std::io::Result<()> {
+        let text = format!("{}{}", "  ".repeat(depth), label);
+        write_row(
+            f,
+            &text,
+            ' ',
+            &text,
+            column_width,
+            Some((self.theme.changed, self.theme.changed)),
+        )
+    }
+
+    fn write_removed(
+        &self,
+        f: &mut dyn WriteColor,
+        label: &str,
+        depth: usize,
+        column_width: usize,
+    ) -> std::io::Result<()> {
+        write_row(
+            f,
+            &format!("{}{}", "  ".repeat(depth), label),
+            '<',
+            "",
+            column_width,
+            Some((self.theme.removed, self.theme.removed)),
+        )
+    }
+
+    fn write_added(
+        &self,
+        f: &mut dyn WriteColor,
+        label: &str,
+        depth: usize,
+        column_width: usize,
+    ) -> std::io::Result<()> {
+        write_row(
+            f,
+            "",
+            '>',
+            &format!("{}{}", "  ".repeat(depth), label),
+            column_width,
+            Some((self.theme.added, self.theme.added)),
+        )
+    }
+}
+
+/// Width of a single column, so that both columns and the marker column fit into `width`
+pub fn column_width(width: usize) -> usize {
+    (width.saturating_sub(3) / 2).max(1)
+}
+
+fn write_row(
+    f: &mut dyn WriteColor,
+    left: &str,
+    marker: char,
+    right: &str,
+    column_width: usize,
+    colors: Option<(Option<Color>, Option<Color>)>,
+) -> std::io::Result<()> {
+    if let Some((left_color, _)) = colors {
+        set_fg(f, left_color)?;
+    }
+    write!(f, "{} ", fit(left, column_width))?;
+    if colors.is_some() {
+        f.reset()?;
+    }
+    write!(f, "{}", marker)?;
+    if let Some((_, right_color)) = colors {
+        set_fg(f, right_color)?;
+    }
+    let right = fit(right, column_width);
+    let right = right.trim_end();
+    if right.is_empty() {
+        writeln!(f)
+    } else {
+        writeln!(f, " {}", right)
+    }
+}
+
+/// Keeps a value on a single line by showing its line breaks as `\n`
+pub(crate) fn single_line(text: &str) -> String {
+    text.replace('\r', "").replace('\n', "\\n")
+}
+
+/// Puts a text on a single line and pads or truncates it to exactly `width` characters
+pub fn fit(text: &str, width: usize) -> String {
+    let single_line = single_line(text);
+    let length = single_line.chars().count();
+    if length > width {
+        let mut truncated: String = single_line.chars().take(width.saturating_sub(1)).collect();
+        truncated.push('…');
+        truncated
+    } else {
+        format!("{}{}", single_line, " ".repeat(width - length))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pads_short_text() {
+        assert_eq!("abc   ", fit("abc", 6));
+    }
+
+    #[test]
+    fn truncates_long_text() {
+        assert_eq!("abcd…", fit("abcdefgh", 5));
+    }
+
+    #[test]
+    fn keeps_line_breaks_visible() {
+        assert_eq!("a\\nb", fit("a\r\nb", 4));
+    }
+}
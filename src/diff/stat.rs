@@ -0,0 +1,321 @@
+use std::collections::{BTreeMap, HashMap};
+
+use termcolor::{NoColor, WriteColor};
+
+use crate::diff::changes::{EntryInfo, GroupDiff, GroupInfo};
+use crate::diff::record::{self, Change, Location, Record};
+use crate::diff::set_fg;
+use crate::diff::theme::Theme;
+use crate::diff::visit::{walk, Visitor};
+
+/// Amount of entries that were added, removed, modified or moved
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EntryCounts {
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+    pub moved: usize,
+}
+
+impl EntryCounts {
+    /// Writes the counts, all counts above zero in the color of their kind of change
+    pub fn write_colored(&self, f: &mut dyn WriteColor, theme: &Theme) -> std::io::Result<()> {
+        let counts = [
+            (self.added, "added", theme.added),
+            (self.removed, "removed", theme.removed),
+            (self.modified, "modified", theme.changed),
+            (self.moved, "moved", theme.moved),
+        ];
+        for (index, (count, label, color)) in counts.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            match color {
+                Some(color) if *count > 0 => {
+                    set_fg(f, Some(*color))?;
+                    write!(f, "{} {}", count, label)?;
+                    f.reset()?;
+                }
+                _ => write!(f, "{} {}", count, label)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Statistics about a DiffResult, as shown by `--stat`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Statistics {
+    pub entries: EntryCounts,
+    pub groups_added: usize,
+    pub groups_removed: usize,
+    /// Amount of added, removed or changed fields per field name
+    pub fields: BTreeMap<String, usize>,
+    /// Entry counts per group directly below the root group. Entries in the root group itself
+    /// are counted under the name of the root group.
+    pub top_level_groups: BTreeMap<String, EntryCounts>,
+}
+
+impl Statistics {
+    /// Collects the statistics from the records of a DiffResult, together with the groups and
+    /// entries inside of added or removed groups. An entry that was removed in one place and
+    /// added in another with the same UUID counts as moved.
+    pub fn from_changes(changes: &GroupDiff) -> Self {
+        let mut records = record::from_changes(changes);
+        let mut nested = Nested::default();
+        walk(changes, &mut nested);
+        records.extend(nested.0);
+        Statistics::from_records(&records)
+    }
+
+    pub fn from_records(records: &[Record]) -> Self {
+        let mut statistics = Statistics::default();
+
+        let added_entries = entries_by_uuid(records, Change::Added);
+        let removed_entries = entries_by_uuid(records, Change::Removed);
+
+        let mut modified_entries: Vec<&Location> = Vec::new();
+
+        for record in records {
+            match (&record.field, &record.location.title, record.change) {
+                (Some(field), _, _) => {
+                    *statistics.fields.entry(field.clone()).or_default() += 1;
+                    if !modified_entries.contains(&&record.location) {
+                        modified_entries.push(&record.location);
+                        statistics.count(&record.location, |counts| counts.modified += 1);
+                    }
+                }
+                (None, Some(_), Change::Added) => {
+                    let moved_from = record
+                        .location
+                        .uuid
+                        .as_ref()
+                        .and_then(|uuid| removed_entries.get(uuid));
+                    match moved_from {
+                        // same group, so the title was changed
+                        Some(from) if from.group_path == record.location.group_path => {
+                            statistics.count(&record.location, |counts| counts.modified += 1)
+                        }
+                        Some(_) => statistics.count(&record.location, |counts| counts.moved += 1),
+                        None => statistics.count(&record.location, |counts| counts.added += 1),
+                    }
+                }
+                (None, Some(_), _) => {
+                    let is_moved = record
+                        .location
+                        .uuid
+                        .as_ref()
+                        .is_some_and(|uuid| added_entries.contains_key(uuid));
+                    // moved entries are counted once, where they were added
+                    if !is_moved {
+                        statistics.count(&record.location, |counts| counts.removed += 1);
+                    }
+                }
+                (None, None, Change::Added) => statistics.groups_added += 1,
+                (None, None, _) => statistics.groups_removed += 1,
+            }
+        }
+
+        statistics
+    }
+
+    fn count<F: Fn(&mut EntryCounts)>(&mut self, location: &Location, update: F) {
+        update(&mut self.entries);
+        let top_level_group = location
+            .group_path
+            .get(1)
+            .or_else(|| location.group_path.first());
+        if let Some(name) = top_level_group {
+            update(self.top_level_groups.entry(name.clone()).or_default());
+        }
+    }
+}
+
+/// Records of the groups and entries inside of added or removed groups, which the records of a
+/// DiffResult only contain as part of their group
+#[derive(Default)]
+struct Nested(Vec<Record>);
+
+impl Nested {
+    fn push(&mut self, change: Change, path: &[&str], uuid: Option<&str>) {
+        let mut group_path: Vec<String> = path.iter().map(|name| name.to_string()).collect();
+        let title = uuid.and_then(|_| group_path.pop());
+        self.0.push(Record {
+            change,
+            location: Location {
+                group_path,
+                title,
+                uuid: uuid.map(str::to_owned),
+            },
+            field: None,
+            old: None,
+            new: None,
+        });
+    }
+}
+
+impl Visitor for Nested {
+    fn nested_group_added(&mut self, path: &[&str], _group: &GroupInfo) {
+        self.push(Change::Added, path, None);
+    }
+
+    fn nested_group_removed(&mut self, path: &[&str], _group: &GroupInfo) {
+        self.push(Change::Removed, path, None);
+    }
+
+    fn nested_entry_added(&mut self, path: &[&str], entry: &EntryInfo) {
+        self.push(Change::Added, path, Some(&entry.uuid));
+    }
+
+    fn nested_entry_removed(&mut self, path: &[&str], entry: &EntryInfo) {
+        self.push(Change::Removed, path, Some(&entry.uuid));
+    }
+}
+
+/// Locations of the entries that were added or removed as a whole, by their UUID
+fn entries_by_uuid(records: &[Record], change: Change) -> HashMap<&String, &Location> {
+    records
+        .iter()
+        .filter(|record| record.change == change && record.field.is_none())
+        .filter_map(|record| {
+            record
+                .location
+                .uuid
+                .as_ref()
+                .map(|uuid| (uuid, &record.location))
+        })
+        .collect()
+}
+
+impl Statistics {
+    pub fn write_colored(&self, f: &mut dyn WriteColor, theme: &Theme) -> std::io::Result<()> {
+        write!(f, "Entries: ")?;
+        self.entries.write_colored(f, theme)?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "Groups: {} added, {} removed",
+            self.groups_added, self.groups_removed
+        )?;
+        if !self.fields.is_empty() {
+            writeln!(f)?;
+            write_heading(f, "Changed fields:", theme)?;
+            for (name, count) in &self.fields {
+                writeln!(f, "  {}: {}", name, count)?;
+            }
+        }
+        if !self.top_level_groups.is_empty() {
+            writeln!(f)?;
+            write_heading(f, "Top-level groups:", theme)?;
+            for (name, counts) in &self.top_level_groups {
+                write!(f, "  {}: ", name)?;
+                counts.write_colored(f, theme)?;
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_heading(f: &mut dyn WriteColor, heading: &str, theme: &Theme) -> std::io::Result<()> {
+    if let Some(color) = theme.metadata {
+        set_fg(f, Some(color))?;
+        write!(f, "{}", heading)?;
+        f.reset()?;
+        writeln!(f)
+    } else {
+        writeln!(f, "{}", heading)
+    }
+}
+
+impl std::fmt::Display for Statistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut plain = NoColor::new(Vec::new());
+        self.write_colored(&mut plain, &Theme::default())
+            .map_err(|_| std::fmt::Error)?;
+        write!(f, "{}", String::from_utf8_lossy(plain.get_ref()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry_record(change: Change, group_path: &[&str], title: &str, uuid: &str) -> Record {
+        Record {
+            change,
+            location: Location {
+                group_path: group_path.iter().map(|name| name.to_string()).collect(),
+                title: Some(title.to_owned()),
+                uuid: Some(uuid.to_owned()),
+            },
+            field: None,
+            old: None,
+            new: None,
+        }
+    }
+
+    #[test]
+    fn counts_entries_with_same_uuid_as_moved() {
+        let statistics = Statistics::from_records(&[
+            entry_record(Change::Removed, &["Root", "A"], "Entry", "1"),
+            entry_record(Change::Added, &["Root", "B"], "Entry", "1"),
+        ]);
+
+        assert_eq!(1, statistics.entries.moved);
+        assert_eq!(0, statistics.entries.added);
+        assert_eq!(0, statistics.entries.removed);
+        assert_eq!(
+            Some(1),
+            statistics.top_level_groups.get("B").map(|c| c.moved)
+        );
+    }
+
+    #[test]
+    fn counts_changed_fields_by_name_and_entry_once() {
+        let mut password = entry_record(Change::Changed, &["Root"], "Entry", "1");
+        password.field = Some("Password".to_owned());
+        let mut url = password.clone();
+        url.field = Some("URL".to_owned());
+        let statistics = Statistics::from_records(&[password, url]);
+
+        assert_eq!(1, statistics.entries.modified);
+        assert_eq!(Some(&1), statistics.fields.get("Password"));
+        assert_eq!(Some(&1), statistics.fields.get("URL"));
+    }
+
+    #[test]
+    fn counts_entries_inside_of_added_groups() {
+        use crate::diff::changes::{EntryChange, GroupChange};
+
+        let entry = |title: &str, uuid: &str| EntryInfo {
+            title: title.to_owned(),
+            uuid: uuid.to_owned(),
+            fields: vec![],
+        };
+        let changes = GroupDiff {
+            name: "Root".to_owned(),
+            groups: vec![GroupChange::Added(GroupInfo {
+                name: "New".to_owned(),
+                groups: vec![GroupInfo {
+                    name: "Sub".to_owned(),
+                    groups: vec![],
+                    entries: vec![entry("Deep", "2")],
+                }],
+                entries: vec![entry("Inside", "1"), entry("Moved", "3")],
+            })],
+            entries: vec![EntryChange::Removed(entry("Moved", "3"))],
+        };
+
+        let statistics = Statistics::from_changes(&changes);
+
+        assert_eq!(2, statistics.entries.added);
+        assert_eq!(1, statistics.entries.moved);
+        assert_eq!(0, statistics.entries.removed);
+        assert_eq!(2, statistics.groups_added);
+        assert_eq!(
+            Some(2),
+            statistics.top_level_groups.get("New").map(|c| c.added)
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use crate::diff::changes::{EntryDiff, EntryInfo, FieldInfo, GroupDiff, GroupInfo};
+use crate::diff::visit::{walk, Visitor};
+use crate::diff::Kind;
+
+/// Amount of added, removed and changed objects of a single kind
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+impl Counts {
+    pub fn total(&self) -> usize {
+        self.added + self.removed + self.changed
+    }
+}
+
+/// Totals of all differences found in a DiffResult tree. Changed groups and entries are counted
+/// as well as the changes inside of them, and so are the groups and entries inside of added or
+/// removed groups.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub groups: Counts,
+    pub entries: Counts,
+    pub fields: Counts,
+}
+
+impl Summary {
+    pub fn from_changes(changes: &GroupDiff) -> Self {
+        let mut summary = Summary::default();
+        walk(changes, &mut summary);
+        summary
+    }
+
+    pub fn counts_mut(&mut self, kind: Kind) -> &mut Counts {
+        match kind {
+            Kind::Group => &mut self.groups,
+            Kind::Entry => &mut self.entries,
+            Kind::Field => &mut self.fields,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.total() + self.entries.total() + self.fields.total() == 0
+    }
+}
+
+impl Visitor for Summary {
+    fn enter_group(&mut self, _path: &[&str], _group: &GroupDiff) {
+        self.groups.changed += 1;
+    }
+
+    fn group_added(&mut self, _path: &[&str], _group: &GroupInfo) {
+        self.groups.added += 1;
+    }
+
+    fn group_removed(&mut self, _path: &[&str], _group: &GroupInfo) {
+        self.groups.removed += 1;
+    }
+
+    fn enter_entry(&mut self, _path: &[&str], _entry: &EntryDiff) {
+        self.entries.changed += 1;
+    }
+
+    fn entry_added(&mut self, _path: &[&str], _entry: &EntryInfo) {
+        self.entries.added += 1;
+    }
+
+    fn entry_removed(&mut self, _path: &[&str], _entry: &EntryInfo) {
+        self.entries.removed += 1;
+    }
+
+    fn field_added(&mut self, _path: &[&str], _field: &FieldInfo) {
+        self.fields.added += 1;
+    }
+
+    fn field_removed(&mut self, _path: &[&str], _field: &FieldInfo) {
+        self.fields.removed += 1;
+    }
+
+    fn field_changed(&mut self, _path: &[&str], _old: &str, _new: &str) {
+        self.fields.changed += 1;
+    }
+
+    fn nested_group_added(&mut self, _path: &[&str], _group: &GroupInfo) {
+        self.groups.added += 1;
+    }
+
+    fn nested_group_removed(&mut self, _path: &[&str], _group: &GroupInfo) {
+        self.groups.removed += 1;
+    }
+
+    fn nested_entry_added(&mut self, _path: &[&str], _entry: &EntryInfo) {
+        self.entries.added += 1;
+    }
+
+    fn nested_entry_removed(&mut self, _path: &[&str], _entry: &EntryInfo) {
+        self.entries.removed += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::diff::changes::GroupChange;
+
+    fn entry(title: &str) -> EntryInfo {
+        EntryInfo {
+            title: title.to_owned(),
+            uuid: title.to_owned(),
+            fields: vec![],
+        }
+    }
+
+    #[test]
+    fn counts_the_contents_of_added_and_removed_groups() {
+        let diff = GroupDiff {
+            name: "Root".to_owned(),
+            groups: vec![
+                GroupChange::Added(GroupInfo {
+                    name: "New".to_owned(),
+                    groups: vec![],
+                    entries: vec![entry("One"), entry("Two")],
+                }),
+                GroupChange::Removed(GroupInfo {
+                    name: "Old".to_owned(),
+                    groups: vec![GroupInfo {
+                        name: "Sub".to_owned(),
+                        groups: vec![],
+                        entries: vec![entry("Deep")],
+                    }],
+                    entries: vec![],
+                }),
+            ],
+            entries: vec![],
+        };
+
+        let summary = Summary::from_changes(&diff);
+
+        assert_eq!(
+            Counts {
+                added: 1,
+                removed: 2,
+                changed: 1,
+            },
+            summary.groups
+        );
+        assert_eq!(
+            Counts {
+                added: 2,
+                removed: 1,
+                changed: 0,
+            },
+            summary.entries
+        );
+    }
+}
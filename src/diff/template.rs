@@ -0,0 +1,224 @@
+use crate::diff::changes::GroupDiff;
+use crate::diff::path::PathStyle;
+use crate::diff::record::{self, Record};
+
+/// The values of a Record that can be used in a template
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Placeholder {
+    Kind,
+    Path,
+    Title,
+    Uuid,
+    Field,
+    Old,
+    New,
+}
+
+impl Placeholder {
+    fn from_name(name: &str) -> Option<Placeholder> {
+        match name {
+            "kind" => Some(Placeholder::Kind),
+            "path" => Some(Placeholder::Path),
+            "title" => Some(Placeholder::Title),
+            "uuid" => Some(Placeholder::Uuid),
+            "field" => Some(Placeholder::Field),
+            "old" => Some(Placeholder::Old),
+            "new" => Some(Placeholder::New),
+            _ => None,
+        }
+    }
+
+    fn value(&self, record: &Record, path_style: PathStyle) -> String {
+        match self {
+            Placeholder::Kind => record.change.to_string(),
+            Placeholder::Path => record.location.group_path(path_style),
+            Placeholder::Title => record.location.title.clone().unwrap_or_default(),
+            Placeholder::Uuid => record.location.uuid.clone().unwrap_or_default(),
+            Placeholder::Field => record.field.clone().unwrap_or_default(),
+            Placeholder::Old => record.old.clone().unwrap_or_default(),
+            Placeholder::New => record.new.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+/// A user-defined format for a single Record, like `{kind} {path} {title}: {field}`.
+/// Available placeholders are `{kind}`, `{path}`, `{title}`, `{uuid}`, `{field}`, `{old}` and
+/// `{new}`. Literal braces are written as `{{` and `}}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+/// Describes why a template could not be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TemplateError(pub String);
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid template: {}", self.0)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl std::str::FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => {
+                                return Err(TemplateError(format!(
+                                    "placeholder '{{{}' is not closed",
+                                    name
+                                )))
+                            }
+                        }
+                    }
+                    let placeholder = Placeholder::from_name(&name).ok_or_else(|| {
+                        TemplateError(format!("unknown placeholder '{{{}}}'", name))
+                    })?;
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Placeholder(placeholder));
+                }
+                '}' => {
+                    return Err(TemplateError(
+                        "unmatched '}', use '}}' for a literal brace".to_owned(),
+                    ))
+                }
+                _ => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts })
+    }
+}
+
+impl Template {
+    /// Writes the template for a single record, with `{path}` in the given style. A line break
+    /// is added, unless the template already ends with one.
+    pub fn render(
+        &self,
+        record: &Record,
+        path_style: PathStyle,
+        f: &mut dyn std::fmt::Write,
+    ) -> std::fmt::Result {
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => write!(f, "{}", text)?,
+                Part::Placeholder(placeholder) => {
+                    write!(f, "{}", placeholder.value(record, path_style))?
+                }
+            }
+        }
+        match self.parts.last() {
+            Some(Part::Text(text)) if text.ends_with('\n') => Ok(()),
+            _ => writeln!(f),
+        }
+    }
+}
+
+/// Helper wrapper to impl Display for all differences in a user-defined template
+pub struct TemplateDisplay<'a> {
+    pub changes: &'a GroupDiff,
+    pub template: &'a Template,
+    pub path_style: PathStyle,
+}
+
+impl<'a> std::fmt::Display for TemplateDisplay<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for record in record::from_changes(self.changes) {
+            self.template.render(&record, self.path_style, f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::diff::record::{Change, Location};
+
+    fn record() -> Record {
+        Record {
+            change: Change::Changed,
+            location: Location {
+                group_path: vec!["Root".to_owned(), "Mail".to_owned()],
+                title: Some("Work".to_owned()),
+                uuid: Some("1234".to_owned()),
+            },
+            field: Some("Password".to_owned()),
+            old: Some("old".to_owned()),
+            new: Some("new".to_owned()),
+        }
+    }
+
+    fn render_in(template: &str, path_style: PathStyle) -> String {
+        let template: Template = template.parse().unwrap();
+        let mut result = String::new();
+        template.render(&record(), path_style, &mut result).unwrap();
+        result
+    }
+
+    fn render(template: &str) -> String {
+        render_in(template, PathStyle::Slashes)
+    }
+
+    #[test]
+    fn replaces_placeholders() {
+        assert_eq!(
+            "changed Root/Mail Work (1234) Password: old -> new\n",
+            render("{kind} {path} {title} ({uuid}) {field}: {old} -> {new}")
+        );
+    }
+
+    #[test]
+    fn writes_the_path_in_the_given_style() {
+        assert_eq!("[Root, Mail]\n", render_in("{path}", PathStyle::Brackets));
+        assert_eq!("Root/Mail\n", render_in("{path}", PathStyle::Slashes));
+    }
+
+    #[test]
+    fn keeps_escaped_braces() {
+        assert_eq!("{title} = Work\n", render("{{title}} = {title}"));
+    }
+
+    #[test]
+    fn does_not_add_a_second_line_break() {
+        assert_eq!("Work\n", render("{title}\n"));
+    }
+
+    #[test]
+    fn rejects_unknown_placeholders() {
+        assert!("{password}".parse::<Template>().is_err());
+        assert!("{title".parse::<Template>().is_err());
+        assert!("title}".parse::<Template>().is_err());
+    }
+}
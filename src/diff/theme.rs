@@ -0,0 +1,146 @@
+use termcolor::Color;
+
+/// The colors used for the different kinds of lines in colored output. `None` uses the
+/// default color of the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub added: Option<Color>,
+    pub removed: Option<Color>,
+    pub changed: Option<Color>,
+    pub moved: Option<Color>,
+    /// Headers and other lines that are not a difference themselves
+    pub metadata: Option<Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            added: Some(Color::Green),
+            removed: Some(Color::Red),
+            changed: Some(Color::Yellow),
+            moved: Some(Color::Cyan),
+            metadata: None,
+        }
+    }
+}
+
+/// Describes why a theme could not be loaded
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ThemeError(pub String);
+
+impl std::fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid color theme: {}", self.0)
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+impl Theme {
+    /// Blue and orange instead of green and red, distinguishable with red-green color blindness
+    pub fn colorblind() -> Self {
+        Theme {
+            added: Some(Color::Ansi256(33)),
+            removed: Some(Color::Ansi256(208)),
+            changed: Some(Color::Ansi256(220)),
+            moved: Some(Color::Ansi256(169)),
+            metadata: Some(Color::Ansi256(244)),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        match name {
+            "default" => Some(Theme::default()),
+            "colorblind" => Some(Theme::colorblind()),
+            _ => None,
+        }
+    }
+
+    /// Applies settings like `colorblind:added=blue:moved=208` to this theme. A setting without
+    /// `=` selects a built-in theme, the others set the color of a single kind of line. Colors
+    /// are given by name, as an ANSI 256 color number, as `r,g,b` or as `none`.
+    pub fn apply<'s, I: IntoIterator<Item = &'s str>>(
+        &mut self,
+        settings: I,
+    ) -> Result<(), ThemeError> {
+        for setting in settings.into_iter().map(str::trim) {
+            if setting.is_empty() {
+                continue;
+            }
+            let (key, value) = match setting.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => {
+                    *self = Theme::from_name(setting)
+                        .ok_or_else(|| ThemeError(format!("unknown theme '{}'", setting)))?;
+                    continue;
+                }
+            };
+            let color = parse_color(value)?;
+            match key {
+                "added" => self.added = color,
+                "removed" => self.removed = color,
+                "changed" => self.changed = color,
+                "moved" => self.moved = color,
+                "metadata" => self.metadata = color,
+                _ => return Err(ThemeError(format!("unknown kind of line '{}'", key))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the settings of a config file, one setting per line. Lines starting with `#`
+    /// are ignored.
+    pub fn apply_config(&mut self, config: &str) -> Result<(), ThemeError> {
+        self.apply(
+            config
+                .lines()
+                .filter(|line| !line.trim_start().starts_with('#')),
+        )
+    }
+}
+
+fn parse_color(value: &str) -> Result<Option<Color>, ThemeError> {
+    if value == "none" {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|error| ThemeError(format!("{}", error)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn applies_theme_and_colors_in_order() {
+        let mut theme = Theme::default();
+        theme
+            .apply("colorblind:added=blue:removed=208:metadata=none".split(':'))
+            .unwrap();
+
+        assert_eq!(Some(Color::Blue), theme.added);
+        assert_eq!(Some(Color::Ansi256(208)), theme.removed);
+        assert_eq!(Theme::colorblind().changed, theme.changed);
+        assert_eq!(None, theme.metadata);
+    }
+
+    #[test]
+    fn reads_config_files() {
+        let mut theme = Theme::default();
+        theme
+            .apply_config("# my colors\nmoved = 0,95,255\n\nchanged = magenta\n")
+            .unwrap();
+
+        assert_eq!(Some(Color::Rgb(0, 95, 255)), theme.moved);
+        assert_eq!(Some(Color::Magenta), theme.changed);
+    }
+
+    #[test]
+    fn rejects_unknown_settings() {
+        assert!(Theme::default().apply(["solarized"]).is_err());
+        assert!(Theme::default().apply(["inserted=blue"]).is_err());
+        assert!(Theme::default().apply(["added=bluish"]).is_err());
+    }
+}
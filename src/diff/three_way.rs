@@ -0,0 +1,333 @@
+use serde::{Deserialize, Serialize};
+use termcolor::WriteColor;
+
+use crate::diff::changes::{Change, EntryChange, EntryDiff, FieldChange, GroupChange, GroupDiff};
+use crate::diff::path::{format_path, PathStyle};
+use crate::diff::theme::Theme;
+use crate::diff::{set_fg, WriteColored};
+use crate::locate::extend;
+
+/// Who made a change, compared to the common ancestor
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Origin {
+    /// Only A changed the object
+    OnlyA,
+    /// Only B changed the object
+    OnlyB,
+    /// A and B made the same change
+    Both,
+    /// A and B changed the object in different ways
+    Conflict,
+}
+
+/// A change to a single group, entry or field in a three-way diff
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThreeWayChange {
+    pub origin: Origin,
+    /// The names from the root group down to the changed object, ending with its own name
+    pub path: Vec<String>,
+    /// The UUID of the entry in the common ancestor, for changes of entries and their fields
+    pub uuid: Option<String>,
+    /// What A changed, compared to the common ancestor
+    pub a: Option<Change>,
+    /// What B changed, compared to the common ancestor
+    pub b: Option<Change>,
+}
+
+impl ThreeWayChange {
+    /// A copy of the change with all protected values replaced by
+    /// [`MASK`](crate::diff::changes::MASK)
+    pub fn masked(&self) -> ThreeWayChange {
+        ThreeWayChange {
+            a: self.a.as_ref().map(Change::masked),
+            b: self.b.as_ref().map(Change::masked),
+            ..self.clone()
+        }
+    }
+}
+
+/// Classifies the changes of A and B, both compared to their common ancestor with
+/// [`Group::changes`](crate::diff::group::Group::changes). Groups and entries changed by both
+/// are looked into, so that changes to different fields of the same entry do not conflict.
+pub fn from_changes(a: &GroupDiff, b: &GroupDiff) -> Vec<ThreeWayChange> {
+    let mut changes = Vec::new();
+    merge_group(&[], a, b, &mut changes);
+    changes
+}
+
+fn merge_group(path: &[String], a: &GroupDiff, b: &GroupDiff, changes: &mut Vec<ThreeWayChange>) {
+    let path = extend(path, &a.name);
+    for (group_a, group_b) in pair_by(&a.groups, &b.groups, |group| group.name().to_owned()) {
+        let name = group_a
+            .or(group_b)
+            .map(GroupChange::name)
+            .unwrap_or_default();
+        match (group_a, group_b) {
+            (Some(GroupChange::Modified(a)), Some(GroupChange::Modified(b))) => {
+                merge_group(&path, a, b, changes)
+            }
+            (Some(GroupChange::Modified(a)), None) => {
+                merge_group(&path, a, &GroupDiff::identical(a.name.clone()), changes)
+            }
+            (None, Some(GroupChange::Modified(b))) => {
+                merge_group(&path, &GroupDiff::identical(b.name.clone()), b, changes)
+            }
+            (a, b) => changes.push(classify(
+                extend(&path, name),
+                None,
+                a.cloned().map(Change::Group),
+                b.cloned().map(Change::Group),
+            )),
+        }
+    }
+    let key = |entry: &EntryChange| (entry.title().to_owned(), entry.uuid().to_owned());
+    for (entry_a, entry_b) in pair_by(&a.entries, &b.entries, key) {
+        let entry = entry_a.or(entry_b);
+        let title = entry.map(EntryChange::title).unwrap_or_default();
+        let uuid = entry.map(|entry| entry.uuid().to_owned());
+        match (entry_a, entry_b) {
+            (Some(EntryChange::Modified(a)), Some(EntryChange::Modified(b))) => {
+                merge_entry(&path, a, b, changes)
+            }
+            (Some(EntryChange::Modified(a)), None) => merge_entry(&path, a, &unchanged(a), changes),
+            (None, Some(EntryChange::Modified(b))) => merge_entry(&path, &unchanged(b), b, changes),
+            (a, b) => changes.push(classify(
+                extend(&path, title),
+                uuid,
+                a.cloned().map(Change::Entry),
+                b.cloned().map(Change::Entry),
+            )),
+        }
+    }
+}
+
+fn merge_entry(path: &[String], a: &EntryDiff, b: &EntryDiff, changes: &mut Vec<ThreeWayChange>) {
+    let path = extend(path, &a.title);
+    for (field_a, field_b) in pair_by(&a.fields, &b.fields, |field| field.name().to_owned()) {
+        let name = field_a
+            .or(field_b)
+            .map(FieldChange::name)
+            .unwrap_or_default();
+        changes.push(classify(
+            extend(&path, name),
+            Some(a.uuid.clone()),
+            field_a.cloned().map(Change::Field),
+            field_b.cloned().map(Change::Field),
+        ));
+    }
+}
+
+fn classify(
+    path: Vec<String>,
+    uuid: Option<String>,
+    a: Option<Change>,
+    b: Option<Change>,
+) -> ThreeWayChange {
+    let origin = match (&a, &b) {
+        (Some(_), None) => Origin::OnlyA,
+        (None, Some(_)) => Origin::OnlyB,
+        (Some(a), Some(b)) if a == b => Origin::Both,
+        _ => Origin::Conflict,
+    };
+    ThreeWayChange {
+        origin,
+        path,
+        uuid,
+        a,
+        b,
+    }
+}
+
+/// An entry without changes, to compare the changes of the other side with
+fn unchanged(entry: &EntryDiff) -> EntryDiff {
+    EntryDiff {
+        fields: Vec::new(),
+        ..entry.clone()
+    }
+}
+
+/// Pairs the changes of A and B that belong to the same object, sorted by their key. Changes
+/// with the same key are paired in order.
+fn pair_by<'a, T, K, F>(a: &'a [T], b: &'a [T], key: F) -> Vec<(Option<&'a T>, Option<&'a T>)>
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    let mut keys: Vec<K> = a.iter().chain(b).map(&key).collect();
+    keys.sort();
+    keys.dedup();
+
+    let mut pairs = Vec::new();
+    for k in keys {
+        let mut left = a.iter().filter(|change| key(change) == k);
+        let mut right = b.iter().filter(|change| key(change) == k);
+        loop {
+            match (left.next(), right.next()) {
+                (None, None) => break,
+                pair => pairs.push(pair),
+            }
+        }
+    }
+    pairs
+}
+
+/// Helper wrapper to write a three-way diff as text. Every change gets a line starting with
+/// who made it: `A`, `B` or `A+B`. Conflicts start with `!` and list both changes below.
+pub struct ThreeWayDisplay<'a> {
+    pub changes: &'a [ThreeWayChange],
+    pub path_style: PathStyle,
+    pub theme: Theme,
+}
+
+impl<'a> WriteColored for ThreeWayDisplay<'a> {
+    fn write_colored(&self, f: &mut dyn WriteColor) -> std::io::Result<()> {
+        let result = self
+            .changes
+            .iter()
+            .try_for_each(|change| self.write_change(f, change));
+        f.reset()?;
+        result
+    }
+}
+
+impl<'a> ThreeWayDisplay<'a> {
+    fn write_change(&self, f: &mut dyn WriteColor, change: &ThreeWayChange) -> std::io::Result<()> {
+        let path = format_path(&change.path, self.path_style);
+        let origin = match change.origin {
+            Origin::OnlyA => "A",
+            Origin::OnlyB => "B",
+            Origin::Both => "A+B",
+            Origin::Conflict => {
+                set_fg(f, self.theme.removed)?;
+                writeln!(f, "!   {}", path)?;
+                for (side, edit) in [("A", &change.a), ("B", &change.b)] {
+                    let (marker, value) = describe(edit.as_ref());
+                    let value = value.unwrap_or_else(|| {
+                        match marker {
+                            '+' => "added",
+                            '-' => "removed",
+                            '~' => "modified",
+                            _ => "unchanged",
+                        }
+                        .to_owned()
+                    });
+                    set_fg(f, self.color(marker))?;
+                    writeln!(f, "      {} {} {}", side, marker, value)?;
+                }
+                return Ok(());
+            }
+        };
+        let (marker, value) = describe(change.a.as_ref().or(change.b.as_ref()));
+        set_fg(f, self.color(marker))?;
+        match value {
+            Some(value) => writeln!(f, "{:<3} {} {}: {}", origin, marker, path, value),
+            None => writeln!(f, "{:<3} {} {}", origin, marker, path),
+        }
+    }
+
+    fn color(&self, marker: char) -> Option<termcolor::Color> {
+        match marker {
+            '+' => self.theme.added,
+            '-' => self.theme.removed,
+            '~' => self.theme.changed,
+            _ => None,
+        }
+    }
+}
+
+/// The marker of a change and the values of a field, like `~` and `old → new` for a changed
+/// field. Groups and entries have no values.
+fn describe(change: Option<&Change>) -> (char, Option<String>) {
+    match change {
+        None => (' ', None),
+        Some(Change::Group(GroupChange::Added(_))) | Some(Change::Entry(EntryChange::Added(_))) => {
+            ('+', None)
+        }
+        Some(Change::Group(GroupChange::Removed(_)))
+        | Some(Change::Entry(EntryChange::Removed(_))) => ('-', None),
+        Some(Change::Group(GroupChange::Modified(_)))
+        | Some(Change::Entry(EntryChange::Modified(_))) => ('~', None),
+        Some(Change::Field(FieldChange::Added(field))) => ('+', Some(field.value.clone())),
+        Some(Change::Field(FieldChange::Removed(field))) => ('-', Some(field.value.clone())),
+        Some(Change::Field(FieldChange::Changed { old, new, .. })) => {
+            ('~', Some(format!("{} → {}", old, new)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::diff::changes::EntryInfo;
+
+    fn changed(name: &str, old: &str, new: &str) -> FieldChange {
+        FieldChange::Changed {
+            name: name.to_owned(),
+            old: old.to_owned(),
+            new: new.to_owned(),
+            protected: false,
+        }
+    }
+
+    fn root(entries: Vec<EntryChange>) -> GroupDiff {
+        GroupDiff {
+            name: "Root".to_owned(),
+            groups: vec![],
+            entries,
+        }
+    }
+
+    fn mail(fields: Vec<FieldChange>) -> EntryChange {
+        EntryChange::Modified(EntryDiff {
+            title: "Mail".to_owned(),
+            uuid: "1".to_owned(),
+            fields,
+        })
+    }
+
+    #[test]
+    fn classifies_field_changes() {
+        let a = root(vec![mail(vec![
+            changed("Password", "old", "a"),
+            changed("URL", "old", "new"),
+            changed("UserName", "old", "a"),
+        ])]);
+        let b = root(vec![mail(vec![
+            changed("Notes", "old", "b"),
+            changed("URL", "old", "new"),
+            changed("UserName", "old", "b"),
+        ])]);
+
+        let origins: Vec<_> = from_changes(&a, &b)
+            .into_iter()
+            .map(|change| (change.path.join("/"), change.origin))
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("Root/Mail/Notes".to_owned(), Origin::OnlyB),
+                ("Root/Mail/Password".to_owned(), Origin::OnlyA),
+                ("Root/Mail/URL".to_owned(), Origin::Both),
+                ("Root/Mail/UserName".to_owned(), Origin::Conflict),
+            ],
+            origins
+        );
+    }
+
+    #[test]
+    fn removing_a_modified_entry_conflicts() {
+        let a = root(vec![mail(vec![changed("Password", "old", "a")])]);
+        let b = root(vec![EntryChange::Removed(EntryInfo {
+            title: "Mail".to_owned(),
+            uuid: "1".to_owned(),
+            fields: vec![],
+        })]);
+
+        let changes = from_changes(&a, &b);
+
+        assert_eq!(1, changes.len());
+        assert_eq!(Origin::Conflict, changes[0].origin);
+        assert_eq!(vec!["Root", "Mail"], changes[0].path);
+    }
+}
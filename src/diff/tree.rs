@@ -0,0 +1,136 @@
+use termcolor::{Color, WriteColor};
+
+use crate::diff::changes::{EntryChange, FieldChange, GroupChange, GroupDiff};
+use crate::diff::side_by_side::single_line;
+use crate::diff::theme::Theme;
+use crate::diff::{set_fg, WriteColored};
+
+/// Helper wrapper to write the differences drawn as a tree with box-drawing characters
+pub struct TreeDisplay<'a> {
+    pub changes: &'a GroupDiff,
+    pub theme: Theme,
+}
+
+impl<'a> WriteColored for TreeDisplay<'a> {
+    fn write_colored(&self, f: &mut dyn WriteColor) -> std::io::Result<()> {
+        if self.changes.is_identical() {
+            return Ok(());
+        }
+        format_group(self.changes, f, "", "", &self.theme)
+    }
+}
+
+/// A child in the tree, which can be of any kind
+enum Node<'c> {
+    Group(&'c GroupChange),
+    Entry(&'c EntryChange),
+    Field(&'c FieldChange),
+}
+
+/// Formats a changed group as a line of the tree, followed by its children.
+/// `line_prefix` is written in front of the line of this node, `child_prefix` in front of the
+/// lines of its children.
+fn format_group(
+    diff: &GroupDiff,
+    f: &mut dyn WriteColor,
+    line_prefix: &str,
+    child_prefix: &str,
+    theme: &Theme,
+) -> std::io::Result<()> {
+    write_line(f, line_prefix, theme.changed, &format!("~ {}", diff.name))?;
+    let children: Vec<Node> = diff
+        .groups
+        .iter()
+        .map(Node::Group)
+        .chain(diff.entries.iter().map(Node::Entry))
+        .collect();
+    format_children(&children, f, child_prefix, theme)
+}
+
+fn format_children(
+    children: &[Node],
+    f: &mut dyn WriteColor,
+    child_prefix: &str,
+    theme: &Theme,
+) -> std::io::Result<()> {
+    for (index, child) in children.iter().enumerate() {
+        let (connector, continuation) = if index + 1 == children.len() {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        format_node(
+            child,
+            f,
+            &format!("{}{}", child_prefix, connector),
+            &format!("{}{}", child_prefix, continuation),
+            theme,
+        )?;
+    }
+    Ok(())
+}
+
+/// Fields are shown with their value, groups and entries only with their name
+fn format_node(
+    node: &Node,
+    f: &mut dyn WriteColor,
+    line_prefix: &str,
+    child_prefix: &str,
+    theme: &Theme,
+) -> std::io::Result<()> {
+    match node {
+        Node::Group(GroupChange::Added(group)) => {
+            write_line(f, line_prefix, theme.added, &format!("+ {}", group.name))
+        }
+        Node::Group(GroupChange::Removed(group)) => {
+            write_line(f, line_prefix, theme.removed, &format!("- {}", group.name))
+        }
+        Node::Group(GroupChange::Modified(diff)) => {
+            format_group(diff, f, line_prefix, child_prefix, theme)
+        }
+        Node::Entry(EntryChange::Added(entry)) => {
+            write_line(f, line_prefix, theme.added, &format!("+ {}", entry.title))
+        }
+        Node::Entry(EntryChange::Removed(entry)) => {
+            write_line(f, line_prefix, theme.removed, &format!("- {}", entry.title))
+        }
+        Node::Entry(EntryChange::Modified(diff)) => {
+            write_line(f, line_prefix, theme.changed, &format!("~ {}", diff.title))?;
+            let children: Vec<Node> = diff.fields.iter().map(Node::Field).collect();
+            format_children(&children, f, child_prefix, theme)
+        }
+        Node::Field(FieldChange::Added(field)) => write_line(
+            f,
+            line_prefix,
+            theme.added,
+            &format!("+ {}", field.label(false)),
+        ),
+        Node::Field(FieldChange::Removed(field)) => write_line(
+            f,
+            line_prefix,
+            theme.removed,
+            &format!("- {}", field.label(false)),
+        ),
+        Node::Field(FieldChange::Changed { name, old, new, .. }) => write_line(
+            f,
+            line_prefix,
+            theme.changed,
+            &format!("~ {} = {} → {}", name, old, new),
+        ),
+    }
+}
+
+/// Writes a single line of the tree, line breaks in names and values are shown as `\n` so that
+/// they don't break the connectors
+fn write_line(
+    f: &mut dyn WriteColor,
+    prefix: &str,
+    color: Option<Color>,
+    text: &str,
+) -> std::io::Result<()> {
+    write!(f, "{}", prefix)?;
+    set_fg(f, color)?;
+    write!(f, "{}", single_line(text))?;
+    f.reset()?;
+    writeln!(f)
+}
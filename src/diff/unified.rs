@@ -0,0 +1,51 @@
+use similar::TextDiff;
+
+use crate::diff::group::Group;
+use crate::stack::Stack;
+
+/// Helper wrapper to impl Display for a unified diff between the canonical text
+/// representations of two databases
+pub struct UnifiedDisplay<'a> {
+    pub left: &'a Group,
+    pub right: &'a Group,
+    pub name_a: String,
+    pub name_b: String,
+    pub mask_passwords: bool,
+}
+
+impl<'a> std::fmt::Display for UnifiedDisplay<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text_a = CanonicalDisplay {
+            group: self.left,
+            mask_passwords: self.mask_passwords,
+        }
+        .to_string();
+        let text_b = CanonicalDisplay {
+            group: self.right,
+            mask_passwords: self.mask_passwords,
+        }
+        .to_string();
+
+        let diff = TextDiff::from_lines(&text_a, &text_b);
+        write!(
+            f,
+            "{}",
+            diff.unified_diff()
+                .context_radius(3)
+                .header(&self.name_a, &self.name_b)
+        )
+    }
+}
+
+/// Helper wrapper to impl Display for the canonical text representation of a single database
+pub struct CanonicalDisplay<'a> {
+    pub group: &'a Group,
+    pub mask_passwords: bool,
+}
+
+impl<'a> std::fmt::Display for CanonicalDisplay<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.group
+            .write_canonical(f, &Stack::empty(), self.mask_passwords)
+    }
+}
@@ -0,0 +1,293 @@
+use crate::diff::changes::{
+    EntryChange, EntryDiff, EntryInfo, FieldChange, FieldInfo, GroupChange, GroupDiff, GroupInfo,
+};
+
+/// Receives the differences of a GroupDiff while it is walked with [`walk`], for reporters or
+/// checks that do not want to match on the changes themselves.
+///
+/// Every method gets the full path of the object, starting with the name of the root group and
+/// ending with the name of the object itself. The path of a field ends with the title of its
+/// entry and the name of the field. All methods do nothing by default, so only the interesting
+/// ones need to be implemented.
+pub trait Visitor {
+    /// Called for a group that exists in both databases, before anything inside of it
+    fn enter_group(&mut self, _path: &[&str], _group: &GroupDiff) {}
+
+    /// Called for a group that exists in both databases, after everything inside of it
+    fn leave_group(&mut self, _path: &[&str], _group: &GroupDiff) {}
+
+    fn group_added(&mut self, _path: &[&str], _group: &GroupInfo) {}
+
+    fn group_removed(&mut self, _path: &[&str], _group: &GroupInfo) {}
+
+    /// Called for an entry that exists in both databases, before its fields
+    fn enter_entry(&mut self, _path: &[&str], _entry: &EntryDiff) {}
+
+    /// Called for an entry that exists in both databases, after its fields
+    fn leave_entry(&mut self, _path: &[&str], _entry: &EntryDiff) {}
+
+    fn entry_added(&mut self, _path: &[&str], _entry: &EntryInfo) {}
+
+    fn entry_removed(&mut self, _path: &[&str], _entry: &EntryInfo) {}
+
+    fn field_added(&mut self, _path: &[&str], _field: &FieldInfo) {}
+
+    fn field_removed(&mut self, _path: &[&str], _field: &FieldInfo) {}
+
+    fn field_changed(&mut self, _path: &[&str], _old: &str, _new: &str) {}
+
+    /// Called for every group inside of an added group, after [`Visitor::group_added`] was
+    /// called for the added group itself
+    fn nested_group_added(&mut self, _path: &[&str], _group: &GroupInfo) {}
+
+    /// Called for every group inside of a removed group, after [`Visitor::group_removed`] was
+    /// called for the removed group itself
+    fn nested_group_removed(&mut self, _path: &[&str], _group: &GroupInfo) {}
+
+    /// Called for every entry inside of an added group
+    fn nested_entry_added(&mut self, _path: &[&str], _entry: &EntryInfo) {}
+
+    /// Called for every entry inside of a removed group
+    fn nested_entry_removed(&mut self, _path: &[&str], _entry: &EntryInfo) {}
+}
+
+/// Walks all differences in the same order as the text output: the groups inside of a group
+/// first, followed by its entries. Nothing is visited if the databases are identical.
+pub fn walk<V: Visitor + ?Sized>(changes: &GroupDiff, visitor: &mut V) {
+    if !changes.is_identical() {
+        walk_group(changes, &mut Vec::new(), visitor);
+    }
+}
+
+fn walk_group<'a, V: Visitor + ?Sized>(
+    diff: &'a GroupDiff,
+    path: &mut Vec<&'a str>,
+    visitor: &mut V,
+) {
+    path.push(&diff.name);
+    visitor.enter_group(path, diff);
+    for group in &diff.groups {
+        match group {
+            GroupChange::Added(group) => {
+                path.push(&group.name);
+                visitor.group_added(path, group);
+                walk_nested(group, path, visitor, true);
+                path.pop();
+            }
+            GroupChange::Removed(group) => {
+                path.push(&group.name);
+                visitor.group_removed(path, group);
+                walk_nested(group, path, visitor, false);
+                path.pop();
+            }
+            GroupChange::Modified(diff) => walk_group(diff, path, visitor),
+        }
+    }
+    for entry in &diff.entries {
+        match entry {
+            EntryChange::Added(entry) => {
+                with_segment(path, &entry.title, |path| visitor.entry_added(path, entry))
+            }
+            EntryChange::Removed(entry) => with_segment(path, &entry.title, |path| {
+                visitor.entry_removed(path, entry)
+            }),
+            EntryChange::Modified(diff) => walk_entry(diff, path, visitor),
+        }
+    }
+    visitor.leave_group(path, diff);
+    path.pop();
+}
+
+/// Walks everything inside of an added or removed group, which is already at the end of `path`
+fn walk_nested<'a, V: Visitor + ?Sized>(
+    group: &'a GroupInfo,
+    path: &mut Vec<&'a str>,
+    visitor: &mut V,
+    added: bool,
+) {
+    for child in &group.groups {
+        path.push(&child.name);
+        if added {
+            visitor.nested_group_added(path, child);
+        } else {
+            visitor.nested_group_removed(path, child);
+        }
+        walk_nested(child, path, visitor, added);
+        path.pop();
+    }
+    for entry in &group.entries {
+        with_segment(path, &entry.title, |path| {
+            if added {
+                visitor.nested_entry_added(path, entry)
+            } else {
+                visitor.nested_entry_removed(path, entry)
+            }
+        });
+    }
+}
+
+fn walk_entry<'a, V: Visitor + ?Sized>(
+    diff: &'a EntryDiff,
+    path: &mut Vec<&'a str>,
+    visitor: &mut V,
+) {
+    path.push(&diff.title);
+    visitor.enter_entry(path, diff);
+    for field in &diff.fields {
+        with_segment(path, field.name(), |path| match field {
+            FieldChange::Added(field) => visitor.field_added(path, field),
+            FieldChange::Removed(field) => visitor.field_removed(path, field),
+            FieldChange::Changed { old, new, .. } => visitor.field_changed(path, old, new),
+        });
+    }
+    visitor.leave_entry(path, diff);
+    path.pop();
+}
+
+/// Calls `visit` with the path extended by a single segment
+fn with_segment<'a, F: FnOnce(&[&str])>(path: &mut Vec<&'a str>, segment: &'a str, visit: F) {
+    path.push(segment);
+    visit(path);
+    path.pop();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    impl Visitor for Log {
+        fn enter_group(&mut self, path: &[&str], _group: &GroupDiff) {
+            self.0.push(format!("enter {}", path.join("/")));
+        }
+
+        fn leave_group(&mut self, path: &[&str], _group: &GroupDiff) {
+            self.0.push(format!("leave {}", path.join("/")));
+        }
+
+        fn group_added(&mut self, path: &[&str], _group: &GroupInfo) {
+            self.0.push(format!("+ {}", path.join("/")));
+        }
+
+        fn entry_removed(&mut self, path: &[&str], _entry: &EntryInfo) {
+            self.0.push(format!("- {}", path.join("/")));
+        }
+
+        fn field_changed(&mut self, path: &[&str], old: &str, new: &str) {
+            self.0
+                .push(format!("~ {}: {} -> {}", path.join("/"), old, new));
+        }
+
+        fn nested_group_removed(&mut self, path: &[&str], _group: &GroupInfo) {
+            self.0.push(format!("  - {}", path.join("/")));
+        }
+
+        fn nested_entry_added(&mut self, path: &[&str], _entry: &EntryInfo) {
+            self.0.push(format!("  + {}", path.join("/")));
+        }
+
+        fn nested_entry_removed(&mut self, path: &[&str], _entry: &EntryInfo) {
+            self.0.push(format!("  - {}", path.join("/")));
+        }
+    }
+
+    fn entry(title: &str) -> EntryInfo {
+        EntryInfo {
+            title: title.to_owned(),
+            uuid: title.to_owned(),
+            fields: vec![],
+        }
+    }
+
+    #[test]
+    fn visits_changes_with_full_paths() {
+        let diff = GroupDiff {
+            name: "Root".to_owned(),
+            groups: vec![GroupChange::Added(GroupInfo {
+                name: "New".to_owned(),
+                groups: vec![],
+                entries: vec![],
+            })],
+            entries: vec![
+                EntryChange::Removed(EntryInfo {
+                    title: "Old".to_owned(),
+                    uuid: "1".to_owned(),
+                    fields: vec![],
+                }),
+                EntryChange::Modified(EntryDiff {
+                    title: "Mail".to_owned(),
+                    uuid: "2".to_owned(),
+                    fields: vec![FieldChange::Changed {
+                        name: "Password".to_owned(),
+                        old: "old".to_owned(),
+                        new: "new".to_owned(),
+                        protected: true,
+                    }],
+                }),
+            ],
+        };
+
+        let mut log = Log::default();
+        walk(&diff, &mut log);
+
+        assert_eq!(
+            vec![
+                "enter Root",
+                "+ Root/New",
+                "- Root/Old",
+                "~ Root/Mail/Password: old -> new",
+                "leave Root",
+            ],
+            log.0
+        );
+    }
+
+    #[test]
+    fn visits_nothing_if_identical() {
+        let mut log = Log::default();
+        walk(&GroupDiff::identical("Root".to_owned()), &mut log);
+
+        assert!(log.0.is_empty());
+    }
+
+    #[test]
+    fn visits_the_contents_of_added_and_removed_groups() {
+        let diff = GroupDiff {
+            name: "Root".to_owned(),
+            groups: vec![
+                GroupChange::Added(GroupInfo {
+                    name: "New".to_owned(),
+                    groups: vec![],
+                    entries: vec![entry("Inside")],
+                }),
+                GroupChange::Removed(GroupInfo {
+                    name: "Old".to_owned(),
+                    groups: vec![GroupInfo {
+                        name: "Sub".to_owned(),
+                        groups: vec![],
+                        entries: vec![entry("Deep")],
+                    }],
+                    entries: vec![],
+                }),
+            ],
+            entries: vec![],
+        };
+
+        let mut log = Log::default();
+        walk(&diff, &mut log);
+
+        assert_eq!(
+            vec![
+                "enter Root",
+                "+ Root/New",
+                "  + Root/New/Inside",
+                "  - Root/Old/Sub",
+                "  - Root/Old/Sub/Deep",
+                "leave Root",
+            ],
+            log.0
+        );
+    }
+}
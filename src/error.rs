@@ -0,0 +1,156 @@
+use keepass::error::{
+    CryptographyError, DatabaseIntegrityError, DatabaseKeyError, DatabaseOpenError,
+    DatabaseSaveError,
+};
+
+use std::io;
+
+/// Which of the compared databases something belongs to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    A,
+    B,
+    /// The common ancestor of a three-way diff
+    Base,
+    /// A changeset file that is applied to a database
+    Changeset,
+}
+
+impl std::fmt::Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Side::A => write!(f, "A"),
+            Side::B => write!(f, "B"),
+            Side::Base => write!(f, "BASE"),
+            Side::Changeset => write!(f, "CHANGESET"),
+        }
+    }
+}
+
+/// Why a database could not be opened
+#[derive(Debug)]
+pub enum OpenError {
+    /// The password or key file does not match the database
+    WrongCredentials,
+    /// The key file could not be read
+    MissingKeyfile { path: String, source: io::Error },
+    /// The file is damaged or not a KeePass database at all
+    CorruptFile(String),
+    /// The file is a KeePass database in a version that cannot be read
+    UnsupportedFile,
+    /// The file could not be read
+    Io(io::Error),
+}
+
+impl std::fmt::Display for OpenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OpenError::WrongCredentials => write!(f, "wrong password or key file"),
+            OpenError::MissingKeyfile { path, source } => {
+                write!(f, "cannot read key file '{}': {}", path, source)
+            }
+            OpenError::CorruptFile(reason) => {
+                write!(f, "damaged file or not a KeePass database ({})", reason)
+            }
+            OpenError::UnsupportedFile => write!(f, "unsupported KeePass database version"),
+            OpenError::Io(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for OpenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OpenError::MissingKeyfile { source, .. } => Some(source),
+            OpenError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<DatabaseOpenError> for OpenError {
+    fn from(error: DatabaseOpenError) -> Self {
+        match error {
+            DatabaseOpenError::Io(error) => OpenError::Io(error),
+            DatabaseOpenError::Key(DatabaseKeyError::Io(error)) => OpenError::Io(error),
+            DatabaseOpenError::Key(_) => OpenError::WrongCredentials,
+            // KDBX 3 databases cannot tell a wrong key apart from damaged data, but the padding
+            // of the decrypted data only breaks for a wrong key in practice
+            DatabaseOpenError::DatabaseIntegrity(DatabaseIntegrityError::Cryptography(
+                CryptographyError::Unpadding(_),
+            )) => OpenError::WrongCredentials,
+            DatabaseOpenError::DatabaseIntegrity(error) => {
+                OpenError::CorruptFile(error.to_string())
+            }
+            DatabaseOpenError::UnsupportedVersion => OpenError::UnsupportedFile,
+        }
+    }
+}
+
+/// Everything that can go wrong while comparing or merging databases and writing the results
+#[derive(Debug)]
+pub enum Error {
+    /// One of the databases could not be opened
+    Open {
+        side: Side,
+        file: String,
+        source: OpenError,
+    },
+    /// The differences could not be written
+    Render(io::Error),
+    /// The merged database could not be written
+    Save {
+        file: String,
+        source: DatabaseSaveError,
+    },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Open { side, file, source } => {
+                write!(f, "Error opening database {} '{}': {}", side, file, source)
+            }
+            Error::Render(error) => write!(f, "Error writing output: {}", error),
+            Error::Save { file, source } => {
+                write!(f, "Error saving database '{}': {}", file, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Open { source, .. } => Some(source),
+            Error::Render(error) => Some(error),
+            Error::Save { source, .. } => Some(source),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names_side_and_file() {
+        let error = Error::Open {
+            side: Side::B,
+            file: "b.kdbx".to_owned(),
+            source: DatabaseOpenError::Key(DatabaseKeyError::IncorrectKey).into(),
+        };
+
+        assert_eq!(
+            "Error opening database B 'b.kdbx': wrong password or key file",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn distinguishes_unsupported_files() {
+        let error = OpenError::from(DatabaseOpenError::UnsupportedVersion);
+
+        assert!(matches!(error, OpenError::UnsupportedFile));
+    }
+}
@@ -0,0 +1,65 @@
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// Whether the input names a file in the history of a git repository, like `HEAD~3:vault.kdbx`,
+/// rather than a file. Existing files win, so that paths with a colon can still be read. The
+/// part before the colon has to be a commit or tree that git knows, so that paths like
+/// `C:\vault.kdbx` are reported as missing files. Inputs starting with `-` are never passed to
+/// git, which would take them as options.
+pub fn is_revision(input: &str) -> bool {
+    match input.split_once(':') {
+        Some((revision, _)) if !revision.is_empty() && !input.starts_with('-') => {
+            !Path::new(input).exists() && resolves(revision)
+        }
+        _ => false,
+    }
+}
+
+/// Whether git resolves the revision to a tree in the repository of the current directory
+fn resolves(revision: &str) -> bool {
+    Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet", "--end-of-options"])
+        .arg(format!("{}^{{tree}}", revision))
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Reads a file from the repository of the current directory with `git cat-file`, which takes
+/// the contents as they were committed without any filters
+pub fn read_blob(revision: &str) -> io::Result<Vec<u8>> {
+    let output = Command::new("git")
+        .args(["cat-file", "blob", "--end-of-options", revision])
+        .output()?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        let message = String::from_utf8_lossy(&output.stderr);
+        let message = message.trim().trim_start_matches("fatal: ");
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("git: {}", message),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tells_revisions_from_files() {
+        assert!(is_revision("HEAD:vault.kdbx"));
+        assert!(is_revision("HEAD:test/__fixtures__/test.kdbx"));
+        assert!(!is_revision("test/__fixtures__/test.kdbx"));
+        assert!(!is_revision("vault.kdbx"));
+    }
+
+    #[test]
+    fn takes_unknown_revisions_for_files() {
+        assert!(!is_revision("C:\\vault.kdbx"));
+        assert!(!is_revision("no-such-branch:vault.kdbx"));
+        assert!(!is_revision(":vault.kdbx"));
+        assert!(!is_revision("--output=x:vault.kdbx"));
+    }
+}
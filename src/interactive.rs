@@ -0,0 +1,224 @@
+use keepass_diff::diff::changes::{FieldInfo, GroupInfo, MASK};
+use keepass_diff::diff::path::{format_path, PathStyle};
+use keepass_diff::diff::set_fg;
+use keepass_diff::diff::side_by_side::{column_width, fit};
+use keepass_diff::diff::theme::Theme;
+use keepass_diff::merge::{Choice, Conflict, Version};
+
+use termcolor::WriteColor;
+
+use std::io::BufRead;
+
+/// Walks through the conflicts of a merge on the terminal. Every conflict shows the versions of
+/// A and B next to each other and asks which one to keep. Protected values are masked until
+/// they are revealed.
+pub struct Resolver<'a, R> {
+    pub input: R,
+    pub output: &'a mut dyn WriteColor,
+    pub name_a: String,
+    pub name_b: String,
+    /// Total width of a line, including the marker column in the middle
+    pub width: usize,
+    pub path_style: PathStyle,
+    pub theme: Theme,
+    /// Masks protected values without a way to reveal them
+    pub mask_passwords: bool,
+    /// Set once the user quit, every other conflict keeps the change of A
+    pub quit: bool,
+    /// The first error while reading the answers or writing the conflicts
+    pub error: Option<std::io::Error>,
+}
+
+impl<'a, R: BufRead> Resolver<'a, R> {
+    /// Asks which change of the conflict to keep
+    pub fn resolve(&mut self, conflict: &Conflict) -> Choice {
+        if self.quit || self.error.is_some() {
+            return Choice::A;
+        }
+        match self.ask(conflict) {
+            Ok(Some(choice)) => choice,
+            Ok(None) => {
+                self.quit = true;
+                Choice::A
+            }
+            Err(error) => {
+                self.error = Some(error);
+                Choice::A
+            }
+        }
+    }
+
+    fn ask(&mut self, conflict: &Conflict) -> std::io::Result<Option<Choice>> {
+        let mut revealed = false;
+        loop {
+            self.show(conflict, revealed)?;
+            if self.mask_passwords {
+                write!(self.output, "Keep [a], [b], b[o]th or [q]uit? ")?;
+            } else {
+                write!(
+                    self.output,
+                    "Keep [a], [b], b[o]th, [r]eveal passwords or [q]uit? "
+                )?;
+            }
+            self.output.flush()?;
+
+            let mut answer = String::new();
+            if self.input.read_line(&mut answer)? == 0 {
+                // the input ended, like quitting
+                writeln!(self.output)?;
+                return Ok(None);
+            }
+            match answer.trim().to_lowercase().as_str() {
+                "a" => return Ok(Some(Choice::A)),
+                "b" => return Ok(Some(Choice::B)),
+                "o" => return Ok(Some(Choice::Both)),
+                "q" => return Ok(None),
+                "r" if !self.mask_passwords => revealed = !revealed,
+                _ => writeln!(self.output, "Unknown answer '{}'", answer.trim())?,
+            }
+        }
+    }
+
+    fn show(&mut self, conflict: &Conflict, revealed: bool) -> std::io::Result<()> {
+        let column_width = column_width(self.width);
+        let unmasked = revealed && !self.mask_passwords;
+        let left = lines(conflict.a.as_ref(), unmasked);
+        let right = lines(conflict.b.as_ref(), unmasked);
+
+        writeln!(self.output)?;
+        set_fg(self.output, self.theme.changed)?;
+        writeln!(
+            self.output,
+            "Conflict {} of {}: {}",
+            conflict.index + 1,
+            conflict.count,
+            format_path(&conflict.change.path, self.path_style)
+        )?;
+        set_fg(self.output, self.theme.metadata)?;
+        write_row(self.output, &self.name_a, &self.name_b, column_width)?;
+        let line = "-".repeat(column_width);
+        write_row(self.output, &line, &line, column_width)?;
+        self.output.reset()?;
+        for index in 0..left.len().max(right.len()) {
+            let (left, right) = (
+                left.get(index).map(String::as_str).unwrap_or_default(),
+                right.get(index).map(String::as_str).unwrap_or_default(),
+            );
+            write_row(self.output, left, right, column_width)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_row(
+    f: &mut dyn WriteColor,
+    left: &str,
+    right: &str,
+    column_width: usize,
+) -> std::io::Result<()> {
+    let row = format!("{} | {}", fit(left, column_width), fit(right, column_width));
+    writeln!(f, "{}", row.trim_end())
+}
+
+/// The lines that show a version of a group, entry or field
+fn lines(version: Option<&Version>, unmasked: bool) -> Vec<String> {
+    match version {
+        None => vec!["(does not exist)".to_owned()],
+        Some(Version::Field(field)) => vec![field_line(field, unmasked)],
+        Some(Version::Entry(entry)) => {
+            let mut lines = vec![entry.title.clone()];
+            for field in &entry.fields {
+                lines.push(format!("  {}", field_line(field, unmasked)));
+            }
+            lines
+        }
+        Some(Version::Group(group)) => {
+            let mut lines = Vec::new();
+            group_lines(group, 0, &mut lines);
+            lines
+        }
+    }
+}
+
+fn group_lines(group: &GroupInfo, depth: usize, lines: &mut Vec<String>) {
+    lines.push(format!("{}{}/", "  ".repeat(depth), group.name));
+    for child in &group.groups {
+        group_lines(child, depth + 1, lines);
+    }
+    for entry in &group.entries {
+        lines.push(format!("{}{}", "  ".repeat(depth + 1), entry.title));
+    }
+}
+
+fn field_line(field: &FieldInfo, unmasked: bool) -> String {
+    if field.protected && !unmasked {
+        format!("{} = {}", field.name, MASK)
+    } else {
+        format!("{} = {}", field.name, field.value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use keepass_diff::diff::three_way::{Origin, ThreeWayChange};
+
+    fn password(value: &str) -> Option<Version> {
+        Some(Version::Field(FieldInfo {
+            name: "Password".to_owned(),
+            value: value.to_owned(),
+            protected: true,
+        }))
+    }
+
+    fn resolve(answers: &str, mask_passwords: bool) -> (Choice, String) {
+        let change = ThreeWayChange {
+            origin: Origin::Conflict,
+            path: vec!["Root".to_owned(), "Mail".to_owned(), "Password".to_owned()],
+            uuid: None,
+            a: None,
+            b: None,
+        };
+        let conflict = Conflict {
+            change: &change,
+            a: password("secret-a"),
+            b: password("secret-b"),
+            newer: Choice::A,
+            index: 0,
+            count: 1,
+        };
+        let mut output = termcolor::NoColor::new(Vec::new());
+        let choice = Resolver {
+            input: answers.as_bytes(),
+            output: &mut output,
+            name_a: "a.kdbx".to_owned(),
+            name_b: "b.kdbx".to_owned(),
+            width: 60,
+            path_style: PathStyle::Slashes,
+            theme: Theme::default(),
+            mask_passwords,
+            quit: false,
+            error: None,
+        }
+        .resolve(&conflict);
+        (choice, String::from_utf8(output.into_inner()).unwrap())
+    }
+
+    #[test]
+    fn reveals_passwords_on_request() {
+        let (choice, output) = resolve("r\nb\n", false);
+
+        assert_eq!(Choice::B, choice);
+        let masked = output.find("Password = ***").unwrap();
+        let revealed = output.find("Password = secret-a").unwrap();
+        assert!(masked < revealed);
+    }
+
+    #[test]
+    fn cannot_reveal_masked_passwords() {
+        let (choice, output) = resolve("r\no\n", true);
+
+        assert_eq!(Choice::Both, choice);
+        assert!(!output.contains("secret"));
+    }
+}
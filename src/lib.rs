@@ -0,0 +1,101 @@
+//! The diff engine of `keepass-diff`, to compare two KeePass (.kdbx) databases from other
+//! programs without going through the command line tool.
+//!
+//! A database is opened with [`kdbx_to_group`], which turns its root group into a [`Group`] that
+//! can be compared with [`Group::changes`]. The resulting [`GroupDiff`](diff::changes::GroupDiff)
+//! owns all of its data, can be serialized with serde and can be flattened into
+//! [`Record`](diff::record::Record)s, walked with a [`Visitor`](diff::visit::Visitor) or rendered
+//! by one of the display wrappers in the [`diff`] module, like [`DiffDisplay`](diff::DiffDisplay)
+//! or [`HtmlDisplay`](diff::html::HtmlDisplay).
+//!
+//! ```no_run
+//! extern crate keepass_diff;
+//!
+//! use keepass_diff::diff::record;
+//! use keepass_diff::kdbx_to_group;
+//!
+//! let password = Some("demopass".to_owned());
+//! let a = kdbx_to_group("a.kdbx".to_owned(), password.clone(), None).unwrap();
+//! let b = kdbx_to_group("b.kdbx".to_owned(), password, None).unwrap();
+//!
+//! let changes = a.changes(&b).masked();
+//! if !changes.is_identical() {
+//!     for record in record::from_changes(&changes) {
+//!         println!("{} {}", record.change, record.location.path(Default::default()));
+//!     }
+//! }
+//! ```
+
+extern crate base64;
+pub extern crate keepass;
+extern crate serde;
+extern crate serde_json;
+extern crate similar;
+pub extern crate termcolor;
+
+pub mod changeset;
+pub mod diff;
+pub mod error;
+mod locate;
+pub mod merge;
+pub mod stack;
+pub mod sync;
+#[cfg(test)]
+mod test_util;
+
+use crate::diff::group::Group;
+use crate::error::OpenError;
+use keepass::{Database, DatabaseKey};
+
+use std::fs::File;
+use std::io::Read;
+
+/// Opens a database file with an optional password and key file
+pub fn open_database(
+    file: String,
+    password: Option<String>,
+    keyfile_path: Option<String>,
+) -> Result<Database, OpenError> {
+    let mut source = File::open(file).map_err(OpenError::Io)?;
+    open_database_from(&mut source, password, keyfile_path)
+}
+
+/// Opens a database that is read from `source`, like a database in memory, with an optional
+/// password and key file
+pub fn open_database_from(
+    source: &mut dyn Read,
+    password: Option<String>,
+    keyfile_path: Option<String>,
+) -> Result<Database, OpenError> {
+    let db_key = get_database_key(password, keyfile_path)?;
+    Ok(Database::open(source, db_key)?)
+}
+
+/// Opens a database and converts its root group into a Group that can be diffed
+pub fn kdbx_to_group(
+    file: String,
+    password: Option<String>,
+    keyfile_path: Option<String>,
+) -> Result<Group, OpenError> {
+    let db = open_database(file, password, keyfile_path)?;
+    Ok(Group::from_keepass(&db.root))
+}
+
+/// Builds the key of a database from an optional password and key file
+pub fn get_database_key(
+    password: Option<String>,
+    keyfile_path: Option<String>,
+) -> Result<DatabaseKey, OpenError> {
+    let db_key = DatabaseKey::new();
+    let db_key = match password {
+        Some(pwd) => db_key.with_password(pwd.as_str()),
+        _ => db_key,
+    };
+    if let Some(path) = keyfile_path {
+        File::open(&path)
+            .and_then(|mut keyfile| db_key.with_keyfile(&mut keyfile))
+            .map_err(|source| OpenError::MissingKeyfile { path, source })
+    } else {
+        Ok(db_key)
+    }
+}
@@ -0,0 +1,71 @@
+use keepass::db::{Entry, Group, Node};
+
+pub(crate) fn is_group(node: &Node, name: &str) -> bool {
+    matches!(node, Node::Group(group) if group.name == name)
+}
+
+pub(crate) fn is_entry(node: &Node, uuid: &str) -> bool {
+    matches!(node, Node::Entry(entry) if entry.uuid.to_string() == uuid)
+}
+
+/// The path extended by the name of a group, entry or field inside of it
+pub(crate) fn extend(path: &[String], name: &str) -> Vec<String> {
+    let mut path = path.to_vec();
+    path.push(name.to_owned());
+    path
+}
+
+/// The group at the end of `path`, which starts with the name of the root group like the paths
+/// of the differences
+pub(crate) fn group_at<'a>(root: &'a Group, path: &[String]) -> Option<&'a Group> {
+    let (_, names) = path.split_first()?;
+    names.iter().try_fold(root, |group, name| {
+        group.children.iter().find_map(|node| match node {
+            Node::Group(child) if &child.name == name => Some(child),
+            _ => None,
+        })
+    })
+}
+
+pub(crate) fn group_at_mut<'a>(root: &'a mut Group, path: &[String]) -> Option<&'a mut Group> {
+    let (_, names) = path.split_first()?;
+    names.iter().try_fold(root, |group, name| {
+        group.children.iter_mut().find_map(|node| match node {
+            Node::Group(child) if &child.name == name => Some(child),
+            _ => None,
+        })
+    })
+}
+
+/// The entry with the UUID, or the first entry with the title if the UUID is not found. Entries
+/// are matched by title when they are diffed, so they may have different UUIDs in A and B.
+pub(crate) fn entry_in<'a>(group: &'a Group, uuid: &str, title: &str) -> Option<&'a Entry> {
+    match &group.children[entry_index(group, uuid, title)?] {
+        Node::Entry(entry) => Some(entry),
+        Node::Group(_) => None,
+    }
+}
+
+pub(crate) fn entry_in_mut<'a>(
+    group: &'a mut Group,
+    uuid: &str,
+    title: &str,
+) -> Option<&'a mut Entry> {
+    let index = entry_index(group, uuid, title)?;
+    match &mut group.children[index] {
+        Node::Entry(entry) => Some(entry),
+        Node::Group(_) => None,
+    }
+}
+
+fn entry_index(group: &Group, uuid: &str, title: &str) -> Option<usize> {
+    let has_title = |node: &Node| match node {
+        Node::Entry(entry) => entry.get_title().unwrap_or_default() == title,
+        Node::Group(_) => false,
+    };
+    group
+        .children
+        .iter()
+        .position(|node| is_entry(node, uuid))
+        .or_else(|| group.children.iter().position(has_title))
+}
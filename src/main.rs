@@ -1,186 +1,1215 @@
-extern crate base64;
 extern crate clap;
-extern crate keepass;
+extern crate keepass_diff;
 extern crate rpassword;
+extern crate serde_json;
 extern crate termcolor;
+extern crate terminal_size;
 
-pub mod diff;
-pub mod stack;
+mod git;
+mod interactive;
 
-use clap::Parser;
-use diff::{group::Group, Diff, DiffDisplay};
-use keepass::{error::DatabaseOpenError, Database, DatabaseKey};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use keepass_diff::changeset::{self, Changeset};
+use keepass_diff::diff::{
+    csv::CsvDisplay,
+    group::Group,
+    html::HtmlDisplay,
+    markdown::MarkdownDisplay,
+    path::{format_path, PathStyle},
+    set_fg,
+    side_by_side::SideBySideDisplay,
+    stat::Statistics,
+    template::{Template, TemplateDisplay},
+    theme::Theme,
+    three_way::{self, Origin, ThreeWayDisplay},
+    tree::TreeDisplay,
+    unified::{CanonicalDisplay, UnifiedDisplay},
+    DiffDisplay, WriteColored,
+};
+use keepass_diff::error::{Error, OpenError, Side};
+use keepass_diff::keepass::error::DatabaseSaveError;
+use keepass_diff::keepass::{Database, DatabaseKey};
+use keepass_diff::merge::{self, Policy};
+use keepass_diff::sync;
 
-use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+use crate::interactive::Resolver;
+use keepass_diff::{get_database_key, open_database, open_database_from};
 
-use std::fs::File;
+use termcolor::{ColorChoice, StandardStream, WriteColor};
+
+use std::io::{Cursor, IsTerminal, Write};
+use std::process::ExitCode;
+
+// Exit codes, following the conventions of diff(1)
+const EXIT_IDENTICAL: u8 = 0;
+const EXIT_DIFFERENT: u8 = 1;
+const EXIT_TROUBLE: u8 = 2;
+// Exit codes that tell apart why the databases could not be compared
+const EXIT_WRONG_CREDENTIALS: u8 = 3;
+const EXIT_MISSING_KEYFILE: u8 = 4;
+const EXIT_CORRUPT_FILE: u8 = 5;
+const EXIT_UNSUPPORTED_FILE: u8 = 6;
+const EXIT_IO: u8 = 7;
+const EXIT_RENDER: u8 = 8;
+const EXIT_SAVE: u8 = 9;
+
+/// Stands for an empty database, like for diff(1)
+const NULL_FILE: &str = "/dev/null";
 
 #[derive(Parser, Debug)]
-#[clap(author, version, about, long_about = None)]
+#[clap(
+    author,
+    version,
+    about,
+    long_about = None,
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true,
+    disable_help_subcommand = true
+)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// Sets the first file
-    #[clap(name = "INPUT-A", index = 1)]
-    input_a: String,
+    #[clap(name = "INPUT-A", index = 1, required = true)]
+    input_a: Option<String>,
 
     /// Sets the second file
-    #[clap(name = "INPUT-B", index = 2)]
-    input_b: String,
+    #[clap(name = "INPUT-B", index = 2, required = true)]
+    input_b: Option<String>,
 
-    /// Disables color output
-    #[clap(short = 'C', long = "no-color")]
+    /// Sets a common ancestor of both files and shows who made each change (three-way diff)
+    #[clap(long, value_name = "BASE", conflicts_with_all = ["template", "template_file", "stat", "stat_only"])]
+    base: Option<String>,
+
+    /// Writes the differences into an encrypted changeset file, to apply them with the patch command
+    #[clap(long, value_name = "FILE", conflicts_with = "base")]
+    changeset: Option<String>,
+
+    /// Sets the password of the changeset file (will be asked for if omitted)
+    #[clap(
+        name = "changeset-password",
+        long,
+        value_name = "PASSWORD",
+        global = true
+    )]
+    changeset_password: Option<String>,
+
+    /// Disables color output (same as --color never)
+    #[clap(short = 'C', long = "no-color", global = true)]
     no_color: bool,
 
+    /// Sets when to use colors, 'auto' only uses them when writing to a terminal
+    #[clap(long, value_enum, value_name = "WHEN", default_value_t = ColorWhen::Auto, global = true)]
+    color: ColorWhen,
+
+    /// Sets the color theme, like 'colorblind' or 'colorblind:added=blue:removed=208'
+    #[clap(long, global = true)]
+    theme: Option<String>,
+
     /// Enables verbose output
-    #[clap(short = 'v', long)]
+    #[clap(short = 'v', long, global = true)]
     verbose: bool,
 
     /// Enables verbose output
-    #[clap(short = 'm', long = "mask-passwords")]
+    #[clap(short = 'm', long = "mask-passwords", global = true)]
     mask_passwords: bool,
 
+    /// Prints paths like 'Root/Group/Entry' instead of '[Root, Group, Entry]'
+    #[clap(long = "slash-paths", global = true)]
+    slash_paths: bool,
+
     /// Sets the password for the first file (will be asked for if omitted)
-    #[clap(name = "password-a", long)]
+    #[clap(name = "password-a", long, global = true)]
     password_a: Option<String>,
 
     /// Sets the password for the second file (will be asked for if omitted)
-    #[clap(name = "password-b", long)]
+    #[clap(name = "password-b", long, global = true)]
     password_b: Option<String>,
 
     /// Sets the password for both files (if it's the same for both files)
-    #[clap(name = "passwords", long)]
+    #[clap(name = "passwords", long, global = true)]
     passwords: Option<String>,
 
     /// Asks for password only once, and tries to open both files with it
-    #[clap(name = "same-password", long)]
+    #[clap(name = "same-password", long, global = true)]
     same_password: bool,
 
+    /// Sets the password for the base file (will be asked for if omitted)
+    #[clap(name = "password-base", long, global = true)]
+    password_base: Option<String>,
+
     /// Sets no password for the first file (and will not ask for it)
-    #[clap(name = "no-password-a", long)]
+    #[clap(name = "no-password-a", long, global = true)]
     no_password_a: bool,
 
     /// Sets no password for the second file (and will not ask for it)
-    #[clap(name = "no-password-b", long)]
+    #[clap(name = "no-password-b", long, global = true)]
     no_password_b: bool,
 
+    /// Sets no password for the base file (and will not ask for it)
+    #[clap(name = "no-password-base", long, global = true)]
+    no_password_base: bool,
+
     /// Sets no password for both files (and will not ask for both files)
-    #[clap(name = "no-passwords", long)]
+    #[clap(name = "no-passwords", long, global = true)]
     no_passwords: bool,
 
     /// Sets the key file for the first file
-    #[clap(name = "keyfile-a", long)]
+    #[clap(name = "keyfile-a", long, global = true)]
     keyfile_a: Option<String>,
 
     /// Sets the key file for the second file
-    #[clap(name = "keyfile-b", long)]
+    #[clap(name = "keyfile-b", long, global = true)]
     keyfile_b: Option<String>,
 
+    /// Sets the key file for the base file
+    #[clap(name = "keyfile-base", long, global = true)]
+    keyfile_base: Option<String>,
+
     /// Sets the same key file for both files (keyfile-a and keyfile-b would take precedence if set as well)
-    #[clap(name = "keyfiles", long)]
+    #[clap(name = "keyfiles", long, global = true)]
     keyfiles: Option<String>,
+
+    /// Sets the output format
+    #[clap(short = 'f', long, value_enum, default_value_t = OutputFormat::Text, global = true)]
+    format: OutputFormat,
+
+    /// Sets the width of the side-by-side output and of interactive merges (defaults to the width of the terminal)
+    #[clap(long, global = true)]
+    width: Option<usize>,
+
+    /// Prints statistics about the differences after the output
+    #[clap(long, global = true)]
+    stat: bool,
+
+    /// Prints only the statistics about the differences
+    #[clap(long = "stat-only", global = true)]
+    stat_only: bool,
+
+    /// Prints every difference with a template like '{kind} {path} {title}: {field}'
+    #[clap(long, conflicts_with = "format", global = true)]
+    template: Option<String>,
+
+    /// Reads the template for every difference from a file
+    #[clap(long = "template-file", conflicts_with_all = ["format", "template"], global = true)]
+    template_file: Option<String>,
+
+    /// Prints nothing, only exits with 1 if there are differences and 0 if there are none
+    #[clap(short = 'q', long, global = true)]
+    quiet: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Merges the changes that INPUT-A and INPUT-B made to BASE and writes them into a new file
+    Merge(MergeArgs),
+    /// Applies a changeset to INPUT-A and writes the result into a new file
+    Patch(PatchArgs),
+    /// Copies the groups and entries that only exist in INPUT-B into INPUT-A and saves INPUT-A
+    Sync(SyncArgs),
+    /// Prints a stable text form of INPUT, to be used as a textconv filter of git
+    Textconv(TextconvArgs),
+    /// Compares two versions of a database like an external diff of git (GIT_EXTERNAL_DIFF)
+    GitDiff(GitDiffArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct MergeArgs {
+    /// Sets the common ancestor of both files
+    #[clap(name = "base", value_name = "BASE", index = 1)]
+    base: String,
+
+    /// Sets the first file, the merged file keeps its settings and credentials
+    #[clap(name = "INPUT-A", index = 2)]
+    input_a: String,
+
+    /// Sets the second file
+    #[clap(name = "INPUT-B", index = 3)]
+    input_b: String,
+
+    /// Sets the file to write the merged database to
+    #[clap(short = 'o', long, value_name = "FILE")]
+    output: String,
+
+    /// Resolves conflicts by keeping the newer change or the change of A or B (writes nothing if there are conflicts otherwise)
+    #[clap(long, value_enum, value_name = "SIDE")]
+    prefer: Option<Prefer>,
+
+    /// Asks which change to keep for every conflict, showing both versions next to each other
+    #[clap(short = 'i', long, conflicts_with = "prefer")]
+    interactive: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct PatchArgs {
+    /// Sets the changeset file, written with --changeset
+    #[clap(name = "CHANGESET", index = 1)]
+    changeset: String,
+
+    /// Sets the file to apply the changeset to, the patched file keeps its settings and credentials
+    #[clap(name = "INPUT-A", index = 2)]
+    input_a: String,
+
+    /// Sets the file to write the patched database to
+    #[clap(short = 'o', long, value_name = "FILE")]
+    output: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct SyncArgs {
+    /// Sets the file to copy into, it is saved with its settings and credentials
+    #[clap(name = "INPUT-A", index = 1)]
+    input_a: String,
+
+    /// Sets the file to copy from
+    #[clap(name = "INPUT-B", index = 2)]
+    input_b: String,
+
+    /// Sets the file to write the synced database to instead of INPUT-A
+    #[clap(short = 'o', long, value_name = "FILE")]
+    output: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct TextconvArgs {
+    /// Sets the file to print
+    #[clap(name = "INPUT")]
+    input: String,
+}
+
+/// The arguments that git passes to an external diff program, see GIT_EXTERNAL_DIFF in git(1)
+#[derive(clap::Args, Debug)]
+struct GitDiffArgs {
+    /// The path of the database in the repository
+    #[clap(name = "PATH")]
+    path: String,
+
+    /// The old version of the database, /dev/null if it was added
+    #[clap(name = "OLD-FILE")]
+    old_file: String,
+
+    #[clap(name = "OLD-HEX")]
+    old_hex: String,
+
+    #[clap(name = "OLD-MODE")]
+    old_mode: String,
+
+    /// The new version of the database, /dev/null if it was removed
+    #[clap(name = "NEW-FILE")]
+    new_file: String,
+
+    #[clap(name = "NEW-HEX")]
+    new_hex: String,
+
+    #[clap(name = "NEW-MODE")]
+    new_mode: String,
+
+    /// The new path of the database if it was renamed
+    #[clap(name = "NEW-PATH")]
+    new_path: Option<String>,
+
+    /// Describes the rename, ignored
+    #[clap(name = "RENAME-HEADER", hide = true)]
+    rename_header: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Prefer {
+    Newer,
+    A,
+    B,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Html,
+    Markdown,
+    Csv,
+    Json,
+    Unified,
+    SideBySide,
+    Tree,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ColorWhen {
+    Auto,
+    Always,
+    Never,
+}
+
+/// A database given on the command line, with the credentials to open it
+#[derive(Debug, Default)]
+struct Source {
+    file: String,
+    password: Option<String>,
+    keyfile: Option<String>,
+}
+
+impl Source {
+    /// Opens the database. If there is no such file, it may also name a file in the history of
+    /// the git repository of the current directory, like `HEAD~3:vault.kdbx`.
+    fn open(&self, side: Side) -> Result<Database, Error> {
+        let opened = if git::is_revision(&self.file) {
+            git::read_blob(&self.file)
+                .map_err(OpenError::Io)
+                .and_then(|blob| {
+                    open_database_from(
+                        &mut Cursor::new(blob),
+                        self.password.clone(),
+                        self.keyfile.clone(),
+                    )
+                })
+        } else {
+            open_database(
+                self.file.clone(),
+                self.password.clone(),
+                self.keyfile.clone(),
+            )
+        };
+        opened.map_err(|source| self.error(side, source))
+    }
+
+    /// Opens the database together with its key, to save it again with the same credentials
+    fn open_with_key(&self, side: Side) -> Result<(DatabaseKey, Database), Error> {
+        let key = get_database_key(self.password.clone(), self.keyfile.clone())
+            .map_err(|source| self.error(side, source))?;
+        Ok((key, self.open(side)?))
+    }
+
+    /// Opens the root group of the database for a diff, where the null device is an empty
+    /// database like for a diff of files. git passes it for databases that were added or removed.
+    fn open_root(&self, side: Side) -> Result<keepass_diff::keepass::db::Group, Error> {
+        if self.file == NULL_FILE {
+            return Ok(keepass_diff::keepass::db::Group::new("Root"));
+        }
+        Ok(self.open(side)?.root)
+    }
+
+    fn error(&self, side: Side, source: OpenError) -> Error {
+        Error::Open {
+            side,
+            file: self.file.clone(),
+            source,
+        }
+    }
+}
+
+/// The options that every command writes its output with
+struct Output {
+    color_choice: ColorChoice,
+    theme: Theme,
+    path_style: PathStyle,
+    mask_passwords: bool,
+    quiet: bool,
+}
+
+impl Output {
+    /// Prints the warnings about the database, like values that cannot be shown as text
+    fn warn(&self, side: Side, db: &Group) {
+        if self.quiet {
+            return;
+        }
+        for warning in db.warnings() {
+            eprintln!("Warning for database {}: {}", side, warning);
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let matches = Args::command().get_matches();
+    let arguments = Args::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+
+    if let Some((command, option)) = unsupported_option(&matches) {
+        eprintln!("{} cannot be used with {}", option, command);
+        return ExitCode::from(EXIT_TROUBLE);
+    }
+
+    let template = match read_template(&arguments) {
+        Ok(template) => template,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::from(EXIT_TROUBLE);
+        }
+    };
+
+    let theme = match read_theme(&arguments) {
+        Ok(theme) => theme,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::from(EXIT_TROUBLE);
+        }
+    };
+
+    if arguments.base.is_some()
+        && !matches!(arguments.format, OutputFormat::Text | OutputFormat::Json)
+    {
+        eprintln!("A three-way diff with --base can only be written as text or json");
+        return ExitCode::from(EXIT_TROUBLE);
+    }
+
+    let (a, b, base) = sources(&arguments);
+    let output = Output {
+        color_choice: color_choice(arguments.color, arguments.no_color),
+        theme,
+        path_style: if arguments.slash_paths {
+            PathStyle::Slashes
+        } else {
+            PathStyle::Brackets
+        },
+        mask_passwords: arguments.mask_passwords,
+        quiet: arguments.quiet,
+    };
+
+    match (&arguments.command, b, base) {
+        (Some(Command::Textconv(_)), _, _) => run_textconv(a, &output),
+        (Some(Command::Patch(patch)), _, _) => {
+            run_patch(patch, a, arguments.changeset_password.clone(), &output)
+        }
+        (Some(Command::Sync(sync)), Some(b), _) => run_sync(sync, a, b, &output),
+        (Some(Command::Merge(merge)), Some(b), Some(base)) => {
+            run_merge(merge, base, a, b, arguments.width, &output)
+        }
+        (_, b, base) => run_diff(
+            &arguments,
+            template.as_ref(),
+            a,
+            b.unwrap_or_default(),
+            base,
+            &output,
+        ),
+    }
+}
+
+/// The databases A, B and BASE of the command, with the passwords that were given or asked for
+fn sources(arguments: &Args) -> (Source, Option<Source>, Option<Source>) {
+    let (file_a, file_b, base) = match &arguments.command {
+        Some(Command::Merge(merge)) => (
+            merge.input_a.clone(),
+            Some(merge.input_b.clone()),
+            Some(merge.base.clone()),
+        ),
+        Some(Command::Patch(patch)) => (patch.input_a.clone(), None, None),
+        Some(Command::Sync(sync)) => (sync.input_a.clone(), Some(sync.input_b.clone()), None),
+        Some(Command::Textconv(textconv)) => (textconv.input.clone(), None, None),
+        Some(Command::GitDiff(git_diff)) => (
+            git_diff.old_file.clone(),
+            Some(git_diff.new_file.clone()),
+            None,
+        ),
+        None => (
+            arguments.input_a.clone().unwrap_or_default(),
+            arguments.input_b.clone(),
+            arguments.base.clone(),
+        ),
+    };
+    let pass_a = match (
+        arguments.password_a.clone(),
+        arguments.passwords.clone(),
+        arguments.same_password,
+        arguments.no_password_a,
+        arguments.no_passwords,
+    ) {
+        _ if file_a == NULL_FILE => None,
+        (Some(password), _, _, _, _) => Some(password),
+        (_, Some(password), _, _, _) => Some(password),
+        (_, _, true, _, _) => prompt_password("Password for both files: "),
+        (_, _, _, true, _) => None,
+        (_, _, _, _, true) => None,
+        _ => prompt_password(format!("Password for file {}: ", file_a).as_str()),
+    };
+    let pass_b = match (
+        &file_b,
+        arguments.password_b.clone(),
+        arguments.passwords.clone(),
+        arguments.same_password,
+        arguments.no_password_b,
+        arguments.no_passwords,
+    ) {
+        (None, _, _, _, _, _) => None,
+        (Some(file), _, _, _, _, _) if file == NULL_FILE => None,
+        (_, Some(password), _, _, _, _) => Some(password),
+        (_, _, Some(password), _, _, _) => Some(password),
+        (_, _, _, true, _, _) => pass_a.clone(),
+        (_, _, _, _, true, _) => None,
+        (_, _, _, _, _, true) => None,
+        (Some(file), _, _, _, _, _) => {
+            prompt_password(format!("Password for file {}: ", file).as_str())
+        }
+    };
+    let pass_base = match (
+        &base,
+        arguments.password_base.clone(),
+        arguments.passwords.clone(),
+        arguments.same_password,
+        arguments.no_password_base,
+        arguments.no_passwords,
+    ) {
+        (None, _, _, _, _, _) => None,
+        (_, Some(password), _, _, _, _) => Some(password),
+        (_, _, Some(password), _, _, _) => Some(password),
+        (_, _, _, true, _, _) => pass_a.clone(),
+        (_, _, _, _, true, _) => None,
+        (_, _, _, _, _, true) => None,
+        (Some(file), _, _, _, _, _) => {
+            prompt_password(format!("Password for file {}: ", file).as_str())
+        }
+    };
+    let keyfile_a = arguments.keyfile_a.clone().or(arguments.keyfiles.clone());
+    let keyfile_b = arguments.keyfile_b.clone().or(arguments.keyfiles.clone());
+    let keyfile_base = arguments
+        .keyfile_base
+        .clone()
+        .or(arguments.keyfiles.clone());
+    (
+        Source {
+            file: file_a,
+            password: pass_a,
+            keyfile: keyfile_a,
+        },
+        file_b.map(|file| Source {
+            file,
+            password: pass_b,
+            keyfile: keyfile_b,
+        }),
+        base.map(|file| Source {
+            file,
+            password: pass_base,
+            keyfile: keyfile_base,
+        }),
+    )
+}
+
+fn run_textconv(a: Source, output: &Output) -> ExitCode {
+    let db = match a.open_root(Side::A) {
+        Ok(root) => Group::from_keepass(&root),
+        Err(error) => return fail(error),
+    };
+    let mut stdout = std::io::stdout();
+    let written = write!(
+        stdout,
+        "{}",
+        CanonicalDisplay {
+            group: &db,
+            mask_passwords: output.mask_passwords,
+        }
+    );
+    finish(
+        written.and_then(|_| stdout.flush()),
+        ExitCode::from(EXIT_IDENTICAL),
+    )
 }
 
-fn main() -> Result<(), ()> {
-    let arguments = Args::parse();
-
-    match (arguments.input_a, arguments.input_b) {
-        (file_a, file_b) => {
-            let pass_a = match (
-                arguments.password_a,
-                arguments.passwords.clone(),
-                arguments.same_password,
-                arguments.no_password_a,
-                arguments.no_passwords,
-            ) {
-                (Some(password), _, _, _, _) => Some(String::from(password)),
-                (_, Some(password), _, _, _) => Some(String::from(password)),
-                (_, _, true, _, _) => prompt_password("Password for both files: "),
-                (_, _, _, true, _) => None,
-                (_, _, _, _, true) => None,
-                _ => prompt_password(format!("Password for file {}: ", file_a).as_str()),
-            };
-            let pass_b = match (
-                arguments.password_b,
-                arguments.passwords.clone(),
-                arguments.same_password,
-                arguments.no_password_b,
-                arguments.no_passwords,
-            ) {
-                (Some(password), _, _, _, _) => Some(String::from(password)),
-                (_, Some(password), _, _, _) => Some(String::from(password)),
-                (_, _, true, _, _) => pass_a.clone(),
-                (_, _, _, true, _) => None,
-                (_, _, _, _, true) => None,
-                _ => prompt_password(format!("Password for file {}: ", file_b).as_str()),
-            };
-            let keyfile_a: Option<String> = arguments.keyfile_a.or(arguments.keyfiles.clone());
-            let keyfile_b: Option<String> = arguments.keyfile_b.or(arguments.keyfiles.clone());
-            let use_color: bool = !arguments.no_color;
-            let use_verbose: bool = arguments.verbose;
-            let mask_passwords: bool = arguments.mask_passwords;
-
-            let db_a = kdbx_to_group(file_a, pass_a, keyfile_a, use_verbose, mask_passwords)
-                .expect("Error opening database A");
-            let db_b = kdbx_to_group(file_b, pass_b, keyfile_b, use_verbose, mask_passwords)
-                .expect("Error opening database B");
-
-            let delta = db_a.diff(&db_b);
-
-            println!(
+fn run_patch(
+    patch: &PatchArgs,
+    a: Source,
+    changeset_password: Option<String>,
+    output: &Output,
+) -> ExitCode {
+    let changeset_key = match changeset_key(&patch.changeset, changeset_password) {
+        Some(key) => key,
+        None => return ExitCode::from(EXIT_TROUBLE),
+    };
+    let changeset = std::fs::File::open(&patch.changeset)
+        .map_err(OpenError::Io)
+        .and_then(|mut file| changeset::load(&mut file, changeset_key));
+    let changeset = match changeset {
+        Ok(changeset) => changeset,
+        Err(source) => {
+            return fail(Error::Open {
+                side: Side::Changeset,
+                file: patch.changeset.clone(),
+                source,
+            })
+        }
+    };
+    let (key, mut database) = match a.open_with_key(Side::A) {
+        Ok(opened) => opened,
+        Err(error) => return fail(error),
+    };
+    output.warn(Side::A, &Group::from_keepass(&database.root));
+
+    let mut stdout = StandardStream::stdout(output.color_choice);
+    if let Err(mismatches) = changeset::apply(&mut database, &changeset) {
+        let exit_code = ExitCode::from(EXIT_DIFFERENT);
+        if output.quiet {
+            return exit_code;
+        }
+        let written = mismatches.iter().try_for_each(|mismatch| {
+            writeln!(
+                stdout,
+                "! {}: {}",
+                format_path(&mismatch.path, output.path_style),
+                mismatch.reason
+            )
+        });
+        let written = written.and_then(|_| {
+            writeln!(
+                stdout,
+                "{} does not match the changeset, nothing written",
+                a.file
+            )
+        });
+        return finish(written.and_then(|_| stdout.flush()), exit_code);
+    }
+    if let Err(error) = save_file(&patch.output, |buffer| {
+        merge::save(&mut database, buffer, key)
+    }) {
+        return fail(error);
+    }
+    let exit_code = ExitCode::from(EXIT_IDENTICAL);
+    if output.quiet {
+        return exit_code;
+    }
+    let written = writeln!(stdout, "Patched into {}", patch.output);
+    finish(written.and_then(|_| stdout.flush()), exit_code)
+}
+
+fn run_sync(sync: &SyncArgs, a: Source, b: Source, output: &Output) -> ExitCode {
+    let opened = a
+        .open_with_key(Side::A)
+        .and_then(|(key, database)| Ok((key, database, b.open(Side::B)?)));
+    let (key, mut database, source) = match opened {
+        Ok(opened) => opened,
+        Err(error) => return fail(error),
+    };
+    output.warn(Side::A, &Group::from_keepass(&database.root));
+    output.warn(Side::B, &Group::from_keepass(&source.root));
+
+    let file = match &sync.output {
+        Some(file) => file,
+        None if git::is_revision(&a.file) => {
+            eprintln!("A git revision cannot be saved, use --output");
+            return ExitCode::from(EXIT_TROUBLE);
+        }
+        None => &a.file,
+    };
+    let synced = sync::sync(&mut database, &source);
+    if !synced.added.is_empty() {
+        if let Err(error) = save_file(file, |buffer| merge::save(&mut database, buffer, key)) {
+            return fail(error);
+        }
+    }
+    let exit_code = ExitCode::from(EXIT_IDENTICAL);
+    if output.quiet {
+        return exit_code;
+    }
+
+    let mut stdout = StandardStream::stdout(output.color_choice);
+    let written = synced.added.iter().try_for_each(|path| {
+        set_fg(&mut stdout, output.theme.added)?;
+        writeln!(stdout, "+ {}", format_path(path, output.path_style))?;
+        stdout.reset()
+    });
+    let written = written.and_then(|_| {
+        synced.skipped.iter().try_for_each(|path| {
+            writeln!(
+                stdout,
+                "  {} (skipped, its UUID already exists in A)",
+                format_path(path, output.path_style)
+            )
+        })
+    });
+    let written = written.and_then(|_| {
+        if synced.added.is_empty() {
+            writeln!(stdout, "Nothing to sync, nothing written")
+        } else {
+            writeln!(stdout, "Synced into {}", file)
+        }
+    });
+    finish(written.and_then(|_| stdout.flush()), exit_code)
+}
+
+fn run_merge(
+    merge: &MergeArgs,
+    base: Source,
+    a: Source,
+    b: Source,
+    width: Option<usize>,
+    output: &Output,
+) -> ExitCode {
+    let databases = base.open(Side::Base).and_then(|db_base| {
+        let (key, db_a) = a.open_with_key(Side::A)?;
+        Ok((key, db_base, db_a, b.open(Side::B)?))
+    });
+    let (key, db_base, db_a, db_b) = match databases {
+        Ok(databases) => databases,
+        Err(error) => return fail(error),
+    };
+    output.warn(Side::Base, &Group::from_keepass(&db_base.root));
+    output.warn(Side::A, &Group::from_keepass(&db_a.root));
+    output.warn(Side::B, &Group::from_keepass(&db_b.root));
+
+    let policy = merge.prefer.map(|prefer| match prefer {
+        Prefer::Newer => Policy::PreferNewer,
+        Prefer::A => Policy::PreferA,
+        Prefer::B => Policy::PreferB,
+    });
+    let mut resolver = Resolver {
+        input: std::io::stdin().lock(),
+        output: &mut StandardStream::stdout(output.color_choice),
+        name_a: a.file,
+        name_b: b.file,
+        width: width.unwrap_or_else(terminal_width),
+        path_style: output.path_style,
+        theme: output.theme,
+        mask_passwords: output.mask_passwords,
+        quit: false,
+        error: None,
+    };
+    let mut merged = if merge.interactive {
+        merge::merge_with(&db_base, db_a, &db_b, |conflict| resolver.resolve(conflict))
+    } else {
+        merge::merge(&db_base, db_a, &db_b, policy.unwrap_or(Policy::PreferA))
+    };
+    if let Some(error) = resolver.error {
+        return fail(Error::Render(error));
+    }
+    if resolver.quit {
+        eprintln!("Merge aborted, nothing written");
+        return ExitCode::from(EXIT_DIFFERENT);
+    }
+    let conflicts: Vec<_> = merged
+        .changes
+        .iter()
+        .filter(|change| change.origin == Origin::Conflict)
+        .map(|change| {
+            if output.mask_passwords {
+                change.masked()
+            } else {
+                change.clone()
+            }
+        })
+        .collect();
+    let unresolved = policy.is_none() && !merge.interactive && !conflicts.is_empty();
+
+    if !unresolved {
+        if let Err(error) = save_file(&merge.output, |buffer| {
+            merge::save(&mut merged.database, buffer, key)
+        }) {
+            return fail(error);
+        }
+    }
+    let exit_code = if unresolved {
+        ExitCode::from(EXIT_DIFFERENT)
+    } else {
+        ExitCode::from(EXIT_IDENTICAL)
+    };
+    if output.quiet {
+        return exit_code;
+    }
+
+    let mut stdout = StandardStream::stdout(output.color_choice);
+    // the conflicts were already shown while resolving them interactively
+    let reported = if merge.interactive {
+        0
+    } else {
+        conflicts.len()
+    };
+    let written = conflicts
+        .iter()
+        .zip(&merged.resolutions)
+        .take(reported)
+        .try_for_each(|(change, resolution)| {
+            ThreeWayDisplay {
+                changes: std::slice::from_ref(change),
+                path_style: output.path_style,
+                theme: output.theme,
+            }
+            .write_colored(&mut stdout)?;
+            if policy.is_some() {
+                writeln!(stdout, "      kept {}", resolution.kept)?;
+            }
+            Ok(())
+        });
+    let written = written.and_then(|_| {
+        if unresolved {
+            writeln!(
+                stdout,
+                "{} conflicts, nothing written (use --prefer to resolve them)",
+                conflicts.len()
+            )
+        } else {
+            writeln!(stdout, "Merged into {}", merge.output)
+        }
+    });
+    finish(written.and_then(|_| stdout.flush()), exit_code)
+}
+
+/// Compares A and B, or both with BASE for a three-way diff, and prints the differences
+fn run_diff(
+    arguments: &Args,
+    template: Option<&Template>,
+    a: Source,
+    b: Source,
+    base: Option<Source>,
+    output: &Output,
+) -> ExitCode {
+    let theme = output.theme;
+    let path_style = output.path_style;
+    let use_verbose = arguments.verbose;
+    // git passes temporary files, so the path in the repository is shown instead
+    let (name_a, name_b, git_diff) = match &arguments.command {
+        Some(Command::GitDiff(git_diff)) => {
+            let new_path = git_diff.new_path.as_ref().unwrap_or(&git_diff.path);
+            (
+                format!("a/{}", git_diff.path),
+                format!("b/{}", new_path),
+                true,
+            )
+        }
+        _ => (a.file.clone(), b.file.clone(), false),
+    };
+    let roots = a
+        .open_root(Side::A)
+        .and_then(|root_a| Ok((root_a, b.open_root(Side::B)?)));
+    let (root_a, root_b) = match roots {
+        Ok(roots) => roots,
+        Err(error) => return fail(error),
+    };
+    let db_a = Group::from_keepass(&root_a);
+    let db_b = Group::from_keepass(&root_b);
+
+    let db_base = match base.map(|base| base.open(Side::Base)) {
+        Some(Ok(db)) => Some(Group::from_keepass(&db.root)),
+        Some(Err(error)) => return fail(error),
+        None => None,
+    };
+
+    output.warn(Side::A, &db_a);
+    output.warn(Side::B, &db_b);
+    if let Some(db_base) = &db_base {
+        output.warn(Side::Base, db_base);
+    }
+
+    if let Some(db_base) = db_base {
+        let (changes_a, changes_b) = (db_base.changes(&db_a), db_base.changes(&db_b));
+        let (changes_a, changes_b) = if output.mask_passwords {
+            (changes_a.masked(), changes_b.masked())
+        } else {
+            (changes_a, changes_b)
+        };
+        let changes = three_way::from_changes(&changes_a, &changes_b);
+        let exit_code = if changes.is_empty() {
+            ExitCode::from(EXIT_IDENTICAL)
+        } else {
+            ExitCode::from(EXIT_DIFFERENT)
+        };
+        if output.quiet {
+            return exit_code;
+        }
+
+        let mut stdout = StandardStream::stdout(output.color_choice);
+        let written = match arguments.format {
+            OutputFormat::Json => serde_json::to_writer_pretty(&mut stdout, &changes)
+                .map_err(std::io::Error::from)
+                .and_then(|_| writeln!(stdout)),
+            _ => ThreeWayDisplay {
+                changes: &changes,
+                path_style,
+                theme,
+            }
+            .write_colored(&mut stdout),
+        };
+        return finish(written.and_then(|_| stdout.flush()), exit_code);
+    }
+
+    let changes = db_a.changes(&db_b);
+    if let Some(file) = &arguments.changeset {
+        let key = match changeset_key(file, arguments.changeset_password.clone()) {
+            Some(key) => key,
+            None => return ExitCode::from(EXIT_TROUBLE),
+        };
+        let changeset = Changeset::new(&changes, &root_b);
+        if let Err(error) = save_file(file, |buffer| changeset::save(&changeset, buffer, key)) {
+            return fail(error);
+        }
+    }
+    let changes = if output.mask_passwords {
+        changes.masked()
+    } else {
+        changes
+    };
+    // git stops showing differences as soon as an external diff does not exit with 0
+    let exit_code = if changes.is_identical() || git_diff {
+        ExitCode::from(EXIT_IDENTICAL)
+    } else {
+        ExitCode::from(EXIT_DIFFERENT)
+    };
+
+    if output.quiet {
+        return exit_code;
+    }
+
+    let statistics = if arguments.stat || arguments.stat_only {
+        Some(Statistics::from_changes(&changes))
+    } else {
+        None
+    };
+
+    let mut stdout = StandardStream::stdout(output.color_choice);
+    let written = if git_diff {
+        set_fg(&mut stdout, theme.metadata)
+            .and_then(|_| writeln!(stdout, "keepass-diff {} {}", name_a, name_b))
+            .and_then(|_| stdout.reset())
+    } else {
+        Ok(())
+    };
+    let written = if written.is_err() || arguments.stat_only {
+        written
+    } else if let Some(template) = template {
+        write!(
+            stdout,
+            "{}",
+            TemplateDisplay {
+                changes: &changes,
+                template,
+                path_style,
+            }
+        )
+    } else {
+        match arguments.format {
+            OutputFormat::Text => DiffDisplay {
+                changes: &changes,
+                use_verbose,
+                path_style,
+                theme,
+            }
+            .write_colored(&mut stdout)
+            .and_then(|_| writeln!(stdout)),
+            OutputFormat::Html => write!(
+                stdout,
                 "{}",
-                DiffDisplay {
-                    inner: delta,
-                    path: stack::Stack::empty(),
-                    use_color,
+                HtmlDisplay {
+                    changes: &changes,
+                    name_a,
+                    name_b,
                     use_verbose,
-                    mask_passwords,
                 }
-            );
+            ),
+            OutputFormat::Markdown => write!(
+                stdout,
+                "{}",
+                MarkdownDisplay {
+                    changes: &changes,
+                    name_a,
+                    name_b,
+                }
+            ),
+            OutputFormat::Csv => write!(stdout, "{}", CsvDisplay { changes: &changes }),
+            OutputFormat::Json => serde_json::to_writer_pretty(&mut stdout, &changes)
+                .map_err(std::io::Error::from)
+                .and_then(|_| writeln!(stdout)),
+            OutputFormat::Unified => write!(
+                stdout,
+                "{}",
+                UnifiedDisplay {
+                    left: &db_a,
+                    right: &db_b,
+                    name_a,
+                    name_b,
+                    mask_passwords: output.mask_passwords,
+                }
+            ),
+            OutputFormat::SideBySide => SideBySideDisplay {
+                changes: &changes,
+                name_a,
+                name_b,
+                width: arguments.width.unwrap_or_else(terminal_width),
+                use_verbose,
+                theme,
+            }
+            .write_colored(&mut stdout),
+            OutputFormat::Tree => TreeDisplay {
+                changes: &changes,
+                theme,
+            }
+            .write_colored(&mut stdout),
         }
+    };
+    let written = written.and_then(|_| match statistics {
+        Some(statistics) => statistics.write_colored(&mut stdout, &theme),
+        None => Ok(()),
+    });
+
+    finish(written.and_then(|_| stdout.flush()), exit_code)
+}
+
+/// Returns the exit code if everything was written, or fails with the error otherwise
+fn finish(written: std::io::Result<()>, exit_code: ExitCode) -> ExitCode {
+    match written {
+        Ok(()) => exit_code,
+        // the reader went away, e.g. when piping into head
+        Err(error) if error.kind() == std::io::ErrorKind::BrokenPipe => exit_code,
+        Err(error) => fail(Error::Render(error)),
     }
+}
 
-    Ok(())
+/// Prints the error and returns the exit code for its kind
+fn fail(error: Error) -> ExitCode {
+    eprintln!("{}", error);
+    let code = match error {
+        Error::Open { source, .. } => match source {
+            OpenError::WrongCredentials => EXIT_WRONG_CREDENTIALS,
+            OpenError::MissingKeyfile { .. } => EXIT_MISSING_KEYFILE,
+            OpenError::CorruptFile(_) => EXIT_CORRUPT_FILE,
+            OpenError::UnsupportedFile => EXIT_UNSUPPORTED_FILE,
+            OpenError::Io(_) => EXIT_IO,
+        },
+        Error::Render(_) => EXIT_RENDER,
+        Error::Save { .. } => EXIT_SAVE,
+    };
+    ExitCode::from(code)
 }
 
-fn prompt_password(prompt: &str) -> Option<String> {
-    rpassword::prompt_password(prompt)
-        .map(|s| if s == "" { None } else { Some(s) })
-        .unwrap_or(None)
+/// The first output option on the command line that the subcommand ignores, together with the
+/// name of the subcommand. The output options are global, so that git-diff takes them after its
+/// arguments, but only diffs write their output with them.
+fn unsupported_option(matches: &ArgMatches) -> Option<(&str, &'static str)> {
+    let (command, matches) = matches.subcommand()?;
+    let options: &[(&str, &'static str)] = match command {
+        "git-diff" => &[],
+        // the width is used for the interactive merge
+        "merge" => &[
+            ("format", "--format"),
+            ("verbose", "--verbose"),
+            ("stat", "--stat"),
+            ("stat_only", "--stat-only"),
+            ("template", "--template"),
+            ("template_file", "--template-file"),
+        ],
+        _ => &[
+            ("format", "--format"),
+            ("verbose", "--verbose"),
+            ("stat", "--stat"),
+            ("stat_only", "--stat-only"),
+            ("template", "--template"),
+            ("template_file", "--template-file"),
+            ("width", "--width"),
+        ],
+    };
+    options
+        .iter()
+        .find(|(id, _)| matches.value_source(id) == Some(ValueSource::CommandLine))
+        .map(|(_, option)| (command, *option))
 }
 
-pub fn kdbx_to_group(
-    file: String,
-    password: Option<String>,
-    keyfile_path: Option<String>,
-    use_verbose: bool,
-    mask_passwords: bool,
-) -> Result<Group, DatabaseOpenError> {
-    let db_key = get_database_key(password, keyfile_path)?;
-    let db = Database::open(&mut File::open(file)?, db_key)?;
-    Ok(Group::from_keepass(&db.root, use_verbose, mask_passwords))
+fn read_template(arguments: &Args) -> Result<Option<Template>, Box<dyn std::error::Error>> {
+    let template = match (&arguments.template, &arguments.template_file) {
+        (Some(template), _) => template.clone(),
+        (_, Some(path)) => std::fs::read_to_string(path)?,
+        _ => return Ok(None),
+    };
+    Ok(Some(template.parse()?))
 }
 
-fn get_database_key(
-    password: Option<String>,
-    keyfile_path: Option<String>,
-) -> Result<DatabaseKey, std::io::Error> {
-    let db_key = DatabaseKey::new();
-    let db_key = match password {
-        Some(pwd) => db_key.with_password(pwd.as_str()),
-        _ => db_key,
-    };
-    if let Some(path) = keyfile_path {
-        db_key.with_keyfile(&mut File::open(path)?)
-    } else {
-        Ok(db_key)
+/// The theme starts with the default colors and is changed by the config file, the
+/// KEEPASS_DIFF_COLORS environment variable and the --theme option, in this order.
+fn read_theme(arguments: &Args) -> Result<Theme, Box<dyn std::error::Error>> {
+    let mut theme = Theme::default();
+    if let Some(path) = theme_config_path().filter(|path| path.exists()) {
+        let config = std::fs::read_to_string(&path)?;
+        theme
+            .apply_config(&config)
+            .map_err(|error| format!("{} (in {})", error, path.display()))?;
+    }
+    if let Ok(colors) = std::env::var("KEEPASS_DIFF_COLORS") {
+        theme
+            .apply(colors.split(':'))
+            .map_err(|error| format!("{} (in KEEPASS_DIFF_COLORS)", error))?;
+    }
+    if let Some(colors) = &arguments.theme {
+        theme.apply(colors.split(':'))?;
+    }
+    Ok(theme)
+}
+
+/// `$XDG_CONFIG_HOME/keepass-diff/colors`, falling back to `~/.config/keepass-diff/colors`
+fn theme_config_path() -> Option<std::path::PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|path| !path.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".config"))
+        })?;
+    Some(config_home.join("keepass-diff").join("colors"))
+}
+
+/// Colors are used when asked for explicitly. Otherwise they follow the NO_COLOR and
+/// CLICOLOR_FORCE conventions and are only used if stdout is a terminal.
+fn color_choice(when: ColorWhen, no_color: bool) -> ColorChoice {
+    let is_set = |name: &str, disabled: &str| {
+        std::env::var_os(name).is_some_and(|value| !value.is_empty() && value != disabled)
+    };
+    match (when, no_color) {
+        (_, true) | (ColorWhen::Never, _) => ColorChoice::Never,
+        (ColorWhen::Always, _) => ColorChoice::Always,
+        (ColorWhen::Auto, _) if is_set("NO_COLOR", "") => ColorChoice::Never,
+        (ColorWhen::Auto, _) if is_set("CLICOLOR_FORCE", "0") => ColorChoice::Always,
+        (ColorWhen::Auto, _) if std::io::stdout().is_terminal() => ColorChoice::Auto,
+        (ColorWhen::Auto, _) => ColorChoice::Never,
+    }
+}
+
+fn terminal_width() -> usize {
+    terminal_size::terminal_size()
+        .map(|(terminal_size::Width(width), _)| width as usize)
+        .unwrap_or(80)
+}
+
+/// The key of a changeset file, which needs a password as it contains all changed values
+fn changeset_key(file: &str, password: Option<String>) -> Option<DatabaseKey> {
+    match password.or_else(|| prompt_password(&format!("Password for changeset {}: ", file))) {
+        Some(password) => Some(DatabaseKey::new().with_password(&password)),
+        None => {
+            eprintln!("A changeset needs a password");
+            None
+        }
     }
 }
 
-pub fn set_fg(color: Option<Color>) {
-    let mut stdout = StandardStream::stdout(ColorChoice::Always);
-    stdout.set_color(ColorSpec::new().set_fg(color)).expect("Setting colors in your console failed. Please use the --no-color flag to disable colors if the error persists.");
+/// Writes what `save` serializes into the file. Everything is serialized before the file is
+/// touched, as it may be one of the databases that were read.
+fn save_file<F>(file: &str, save: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut Vec<u8>) -> Result<(), DatabaseSaveError>,
+{
+    let mut buffer = Vec::new();
+    save(&mut buffer)
+        .and_then(|_| Ok(write_file(file, &buffer)?))
+        .map_err(|source| Error::Save {
+            file: file.to_owned(),
+            source,
+        })
+}
+
+/// Writes the file through a temporary file next to it, so that an existing file is only
+/// replaced once everything was written. The file may be one of the databases that were read.
+/// A symbolic link stays a link to the replaced file, which keeps its permissions.
+fn write_file(file: &str, contents: &[u8]) -> std::io::Result<()> {
+    let file = std::fs::canonicalize(file).unwrap_or_else(|_| std::path::PathBuf::from(file));
+    let mut temporary = file.clone().into_os_string();
+    temporary.push(".keepass-diff.tmp");
+    let written = std::fs::File::create(&temporary)
+        .and_then(|mut written| {
+            // before anything is written, as the file is usually only readable by its owner
+            if let Ok(metadata) = std::fs::metadata(&file) {
+                written.set_permissions(metadata.permissions())?;
+            }
+            written.write_all(contents)
+        })
+        .and_then(|_| std::fs::rename(&temporary, &file));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temporary);
+    }
+    written
 }
 
-pub fn reset_color() {
-    let mut stdout = StandardStream::stdout(ColorChoice::Always);
-    stdout.reset().expect("Resetting colors in your console failed. Please use the --no-color flag to disable colors if the error persists.");
+fn prompt_password(prompt: &str) -> Option<String> {
+    rpassword::prompt_password(prompt)
+        .map(|s| if s.is_empty() { None } else { Some(s) })
+        .unwrap_or(None)
 }
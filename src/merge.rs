@@ -0,0 +1,466 @@
+use keepass::config::DatabaseVersion;
+use keepass::db::{Entry, Group, Node, Times};
+use keepass::Database;
+
+use crate::diff::changes::{
+    Change, EntryChange, EntryInfo, FieldChange, FieldInfo, GroupChange, GroupInfo,
+};
+use crate::diff::three_way::{self, Origin, ThreeWayChange};
+use crate::diff::{entry, field, group, Kind};
+use crate::locate::{entry_in, entry_in_mut, group_at, group_at_mut, is_entry, is_group};
+
+use std::collections::HashSet;
+
+/// How a conflict between A and B is resolved
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Policy {
+    /// Keeps the change of the side that modified the group or entry last. If one side removed
+    /// it, the modification of the other side is kept.
+    PreferNewer,
+    PreferA,
+    PreferB,
+}
+
+/// Which change of a conflict is kept
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Choice {
+    A,
+    B,
+    /// Keeps the change of A and adds the version of B as a copy: groups and entries with new
+    /// UUIDs, fields with ` (B)` appended to their name
+    Both,
+}
+
+impl std::fmt::Display for Choice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Choice::A => write!(f, "A"),
+            Choice::B => write!(f, "B"),
+            Choice::Both => write!(f, "both"),
+        }
+    }
+}
+
+/// A conflict and the change that was kept
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Resolution {
+    pub change: ThreeWayChange,
+    pub kept: Choice,
+}
+
+/// The group, entry or field of a conflict as it is in one of the databases
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Version {
+    Group(GroupInfo),
+    Entry(EntryInfo),
+    Field(FieldInfo),
+}
+
+/// A conflict with everything needed to decide which change to keep
+pub struct Conflict<'a> {
+    pub change: &'a ThreeWayChange,
+    /// The version in A, or `None` if it does not exist there
+    pub a: Option<Version>,
+    /// The version in B, or `None` if it does not exist there
+    pub b: Option<Version>,
+    /// The side that modified the group or entry last, or the side that did not remove it
+    pub newer: Choice,
+    /// The position of the conflict, starting with 0
+    pub index: usize,
+    /// The amount of conflicts in the merge
+    pub count: usize,
+}
+
+/// The result of merging two databases
+pub struct Merge {
+    /// A with all changes of B applied that were kept
+    pub database: Database,
+    /// All changes of the three-way diff
+    pub changes: Vec<ThreeWayChange>,
+    /// How the conflicts in `changes` were resolved
+    pub resolutions: Vec<Resolution>,
+}
+
+/// Merges the changes that A and B made to their common ancestor BASE. The merged database
+/// is A itself, so it keeps the settings of A. Changes made only by B are applied to it and
+/// conflicts are resolved with `policy`.
+pub fn merge(base: &Database, a: Database, b: &Database, policy: Policy) -> Merge {
+    merge_with(base, a, b, |conflict| match policy {
+        Policy::PreferNewer => conflict.newer,
+        Policy::PreferA => Choice::A,
+        Policy::PreferB => Choice::B,
+    })
+}
+
+/// Merges like [`merge`], but asks `resolve` which change to keep for every conflict
+pub fn merge_with<F>(base: &Database, a: Database, b: &Database, mut resolve: F) -> Merge
+where
+    F: FnMut(&Conflict) -> Choice,
+{
+    let base_group = group::Group::from_keepass(&base.root);
+    let changes = three_way::from_changes(
+        &base_group.changes(&group::Group::from_keepass(&a.root)),
+        &base_group.changes(&group::Group::from_keepass(&b.root)),
+    );
+    let count = changes
+        .iter()
+        .filter(|change| change.origin == Origin::Conflict)
+        .count();
+
+    let root_a = a.root.clone();
+    let mut database = a;
+    let mut resolutions = Vec::new();
+    let mut touched = HashSet::new();
+    for change in &changes {
+        let kept = match change.origin {
+            Origin::OnlyA | Origin::Both => Choice::A,
+            Origin::OnlyB => Choice::B,
+            Origin::Conflict => {
+                let kept = resolve(&Conflict {
+                    change,
+                    a: version(&root_a, change),
+                    b: version(&b.root, change),
+                    newer: newer(change, &root_a, &b.root),
+                    index: resolutions.len(),
+                    count,
+                });
+                resolutions.push(Resolution {
+                    change: change.clone(),
+                    kept,
+                });
+                kept
+            }
+        };
+        match kept {
+            Choice::A => {}
+            Choice::B => apply(&mut database.root, &b.root, change, &mut touched),
+            Choice::Both => add_copy(&mut database.root, &b.root, change, &mut touched),
+        }
+    }
+
+    Merge {
+        database,
+        changes,
+        resolutions,
+    }
+}
+
+/// Writes the database, encrypted with `key`. KDBX 3 databases are written as KDBX 4, as
+/// that is the only version that can be written.
+pub fn save(
+    database: &mut Database,
+    destination: &mut dyn std::io::Write,
+    key: keepass::DatabaseKey,
+) -> Result<(), keepass::error::DatabaseSaveError> {
+    if let DatabaseVersion::KDB3(_) = database.config.version {
+        database.config.version = DatabaseVersion::KDB4(0);
+    }
+    database.save(destination, key)
+}
+
+/// A group or entry in a database
+enum Object<'a> {
+    Group(&'a Group),
+    Entry(&'a Entry),
+}
+
+fn kind(change: &ThreeWayChange) -> Kind {
+    match change.a.as_ref().or(change.b.as_ref()) {
+        Some(Change::Group(_)) => Kind::Group,
+        Some(Change::Field(_)) => Kind::Field,
+        _ => Kind::Entry,
+    }
+}
+
+/// The changed group or entry, or the entry of a changed field
+fn find<'a>(root: &'a Group, change: &ThreeWayChange) -> Option<Object<'a>> {
+    let (name, parent) = change.path.split_last()?;
+    match kind(change) {
+        Kind::Group => group_at(root, &change.path).map(Object::Group),
+        Kind::Field => {
+            let (title, groups) = parent.split_last()?;
+            entry_in(group_at(root, groups)?, change.uuid.as_deref()?, title).map(Object::Entry)
+        }
+        Kind::Entry => {
+            entry_in(group_at(root, parent)?, change.uuid.as_deref()?, name).map(Object::Entry)
+        }
+    }
+}
+
+fn version(root: &Group, change: &ThreeWayChange) -> Option<Version> {
+    match (kind(change), find(root, change)?) {
+        (Kind::Field, Object::Entry(entry)) => {
+            let name = change.path.last()?;
+            let value = entry.fields.get(name)?;
+            Some(Version::Field(
+                field::Field::from_keepass(name, value).info(),
+            ))
+        }
+        (_, Object::Group(group)) => Some(Version::Group(group::Group::from_keepass(group).info())),
+        (_, Object::Entry(entry)) => Some(Version::Entry(entry::Entry::from_keepass(entry).info())),
+    }
+}
+
+fn newer(change: &ThreeWayChange, a: &Group, b: &Group) -> Choice {
+    let times = |root| match find(root, change) {
+        Some(Object::Group(group)) => Some(&group.times),
+        Some(Object::Entry(entry)) => Some(&entry.times),
+        None => None,
+    };
+    match (times(a), times(b)) {
+        (None, Some(_)) => Choice::B,
+        (Some(times_a), Some(times_b))
+            if times_b.get_last_modification() > times_a.get_last_modification() =>
+        {
+            Choice::B
+        }
+        _ => Choice::A,
+    }
+}
+
+/// Applies the change of B to the merged database. Changes below groups or entries that do
+/// not exist in the merged database are skipped.
+fn apply(merged: &mut Group, b: &Group, change: &ThreeWayChange, touched: &mut HashSet<String>) {
+    let (name, parent) = match change.path.split_last() {
+        Some(split) => split,
+        None => return,
+    };
+    match &change.b {
+        Some(Change::Group(GroupChange::Removed(_))) => {
+            if let Some(group) = group_at_mut(merged, parent) {
+                if let Some(index) = group.children.iter().position(|node| is_group(node, name)) {
+                    group.children.remove(index);
+                }
+            }
+        }
+        Some(Change::Group(_)) => {
+            if let (Some(group), Some(added)) =
+                (group_at_mut(merged, parent), group_at(b, &change.path))
+            {
+                group.children.retain(|node| !is_group(node, name));
+                group.children.push(Node::Group(added.clone()));
+            }
+        }
+        Some(Change::Entry(EntryChange::Removed(entry))) => {
+            if let Some(group) = group_at_mut(merged, parent) {
+                group.children.retain(|node| !is_entry(node, &entry.uuid));
+            }
+        }
+        Some(Change::Entry(change_b)) => {
+            let uuid = change_b.uuid();
+            if let (Some(group), Some(Object::Entry(added))) =
+                (group_at_mut(merged, parent), find(b, change))
+            {
+                group.children.retain(|node| !is_entry(node, uuid));
+                group.children.push(Node::Entry(added.clone()));
+            }
+        }
+        Some(Change::Field(field)) => {
+            let value = match find(b, change) {
+                Some(Object::Entry(entry)) => entry.fields.get(name).cloned(),
+                _ => None,
+            };
+            if let Some(entry) = entry_to_change(merged, change, touched) {
+                match (field, value) {
+                    (FieldChange::Removed(_), _) => {
+                        entry.fields.remove(name);
+                    }
+                    (_, Some(value)) => {
+                        entry.fields.insert(name.clone(), value);
+                    }
+                    (_, None) => {}
+                }
+            }
+        }
+        None => {}
+    }
+}
+
+/// Adds the version of B to the merged database next to the version of A, if B has one
+fn add_copy(merged: &mut Group, b: &Group, change: &ThreeWayChange, touched: &mut HashSet<String>) {
+    let (name, parent) = match change.path.split_last() {
+        Some(split) => split,
+        None => return,
+    };
+    match (kind(change), find(b, change)) {
+        (Kind::Field, Some(Object::Entry(entry_b))) => {
+            let value = match entry_b.fields.get(name) {
+                Some(value) => value.clone(),
+                None => return,
+            };
+            if let Some(entry) = entry_to_change(merged, change, touched) {
+                let name = if entry.fields.contains_key(name) {
+                    format!("{} (B)", name)
+                } else {
+                    name.clone()
+                };
+                entry.fields.insert(name, value);
+            }
+        }
+        (_, Some(Object::Group(group_b))) => {
+            if let Some(group) = group_at_mut(merged, parent) {
+                let mut copy = group_b.clone();
+                if group.children.iter().any(|node| is_group(node, name)) {
+                    renew_uuids(&mut copy);
+                }
+                group.children.push(Node::Group(copy));
+            }
+        }
+        (_, Some(Object::Entry(entry_b))) => {
+            if let Some(group) = group_at_mut(merged, parent) {
+                let mut copy = entry_b.clone();
+                if group
+                    .children
+                    .iter()
+                    .any(|node| is_entry(node, &copy.uuid.to_string()))
+                {
+                    copy.uuid = Entry::new().uuid;
+                }
+                group.children.push(Node::Entry(copy));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The entry of a changed field in the merged database. The first time an entry is changed,
+/// its version before the merge is kept in its history.
+fn entry_to_change<'a>(
+    merged: &'a mut Group,
+    change: &ThreeWayChange,
+    touched: &mut HashSet<String>,
+) -> Option<&'a mut Entry> {
+    let uuid = change.uuid.as_ref()?;
+    let (_, path) = change.path.split_last()?;
+    let (title, groups) = path.split_last()?;
+    let entry = entry_in_mut(group_at_mut(merged, groups)?, uuid, title)?;
+    if touched.insert(uuid.clone()) {
+        let previous = entry.clone();
+        entry
+            .history
+            .get_or_insert_with(Default::default)
+            .add_entry(previous);
+        entry.times.set_last_modification(Times::now());
+    }
+    Some(entry)
+}
+
+/// Gives the group and everything in it new UUIDs, so that it can be added as a copy
+fn renew_uuids(group: &mut Group) {
+    group.uuid = Group::new("").uuid;
+    for node in group.children.iter_mut() {
+        match node {
+            Node::Group(child) => renew_uuids(child),
+            Node::Entry(entry) => entry.uuid = Entry::new().uuid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::entry;
+    use keepass::db::Value;
+
+    fn find_entry<'a>(database: &'a Database, title: &str) -> Option<&'a Entry> {
+        database.root.children.iter().find_map(|node| match node {
+            Node::Entry(entry) if entry.get_title() == Some(title) => Some(entry),
+            _ => None,
+        })
+    }
+
+    fn password(database: &Database, title: &str) -> Option<String> {
+        find_entry(database, title)
+            .and_then(Entry::get_password)
+            .map(str::to_owned)
+    }
+
+    fn set_password(database: &mut Database, title: &str, password: &str) {
+        for node in database.root.children.iter_mut() {
+            if let Node::Entry(entry) = node {
+                if entry.get_title() == Some(title) {
+                    entry.fields.insert(
+                        "Password".to_owned(),
+                        Value::Unprotected(password.to_owned()),
+                    );
+                }
+            }
+        }
+    }
+
+    fn databases() -> (Database, Database, Database) {
+        let mut root = Group::new("Root");
+        root.add_child(entry("Mail", "base"));
+        root.add_child(entry("Bank", "base"));
+        let database = || {
+            let mut database = Database::new(Default::default());
+            database.root = root.clone();
+            database
+        };
+        let (base, mut a, mut b) = (database(), database(), database());
+        set_password(&mut a, "Mail", "a");
+        set_password(&mut b, "Mail", "b");
+        set_password(&mut b, "Bank", "b");
+        b.root.add_child(entry("Forum", "b"));
+        (base, a, b)
+    }
+
+    #[test]
+    fn applies_changes_of_b() {
+        let (base, a, b) = databases();
+
+        let merge = merge(&base, a, &b, Policy::PreferA);
+
+        assert_eq!(Some("a".to_owned()), password(&merge.database, "Mail"));
+        assert_eq!(Some("b".to_owned()), password(&merge.database, "Bank"));
+        assert_eq!(Some("b".to_owned()), password(&merge.database, "Forum"));
+        assert_eq!(1, merge.resolutions.len());
+        assert_eq!(Choice::A, merge.resolutions[0].kept);
+    }
+
+    #[test]
+    fn resolves_conflicts_with_b() {
+        let (base, a, b) = databases();
+
+        let merge = merge(&base, a, &b, Policy::PreferB);
+
+        assert_eq!(Some("b".to_owned()), password(&merge.database, "Mail"));
+        assert_eq!(Choice::B, merge.resolutions[0].kept);
+    }
+
+    #[test]
+    fn keeps_the_previous_version_in_the_history() {
+        let (base, a, b) = databases();
+
+        let merge = merge(&base, a, &b, Policy::PreferB);
+
+        let history = find_entry(&merge.database, "Mail")
+            .and_then(|entry| entry.history.clone())
+            .unwrap();
+        assert_eq!(1, history.get_entries().len());
+        assert_eq!(Some("a"), history.get_entries()[0].get_password());
+    }
+
+    #[test]
+    fn shows_both_versions_and_keeps_both() {
+        let (base, a, b) = databases();
+        let mut versions = Vec::new();
+
+        let merge = merge_with(&base, a, &b, |conflict| {
+            versions.push((conflict.a.clone(), conflict.b.clone()));
+            Choice::Both
+        });
+
+        let field = |value: &str| {
+            Some(Version::Field(FieldInfo {
+                name: "Password".to_owned(),
+                value: value.to_owned(),
+                protected: false,
+            }))
+        };
+        assert_eq!(vec![(field("a"), field("b"))], versions);
+        let mail = find_entry(&merge.database, "Mail").unwrap();
+        assert_eq!(Some("a"), mail.get("Password"));
+        assert_eq!(Some("b"), mail.get("Password (B)"));
+    }
+}
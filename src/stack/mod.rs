@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+/// An immutable stack that shares its tail with the stack it was appended to, used for the path
+/// to a difference while the differences are walked
 pub struct Stack<T> {
     head: Link<T>,
 }
@@ -12,10 +14,12 @@ struct Node<T> {
 }
 
 impl<T> Stack<T> {
+    /// A stack without any values
     pub fn empty() -> Self {
         Stack { head: None }
     }
 
+    /// A new stack with the value on top of this one, which stays unchanged
     pub fn append(&self, value: T) -> Stack<T> {
         Stack {
             head: Some(Rc::new(Node {
@@ -25,29 +29,52 @@ impl<T> Stack<T> {
         }
     }
 
+    /// The value that was appended last
     pub fn head(&self) -> Option<&T> {
         self.head.as_ref().map(|node| &node.value)
     }
 
+    /// The stack without the value that was appended last
     pub fn tail(&self) -> Stack<T> {
         Stack {
             head: self.head.as_ref().and_then(|node| node.next.clone()),
         }
     }
 
+    /// The amount of values
     pub fn len(&self) -> usize {
         match self.head() {
             Some(_) => 1 + self.tail().len(),
             None => 0,
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+
+    /// All values, starting with the first one that was appended
+    pub fn to_vec(&self) -> Vec<&T> {
+        let mut values = Vec::new();
+        let mut node = self.head.as_ref();
+        while let Some(current) = node {
+            values.push(&current.value);
+            node = current.next.as_ref();
+        }
+        values.reverse();
+        values
+    }
 }
 
-impl<T: std::fmt::Display> Stack<T> {
-    pub fn to_string(&self) -> String {
-        self.mk_string("Stack(", ", ", ")")
+impl<T: std::fmt::Display> std::fmt::Display for Stack<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.mk_string("Stack(", ", ", ")"))
     }
+}
 
+impl<T: std::fmt::Display> Stack<T> {
+    /// Joins all values with the separator, starting with the first one that was appended, and
+    /// wraps them with `start` and `end`
     pub fn mk_string(
         &self,
         start: &'static str,
@@ -72,7 +99,7 @@ impl<T: std::fmt::Display> Stack<T> {
                 value,
                 separator,
             ),
-            None => format!(""),
+            None => String::new(),
         }
     }
 }
@@ -128,28 +155,33 @@ mod test {
         assert_eq!(2, stack.append("one").append("two").len())
     }
 
+    #[test]
+    fn to_vec_starts_with_first_value() {
+        assert_eq!(vec![&"a", &"b", &"c", &"d"], stack_abcd().to_vec());
+    }
+
     #[test]
     fn empty_stack() {
         let stack: Stack<&str> = Stack::empty();
-        assert_eq!("Stack()", format!("{}", stack.to_string()))
+        assert_eq!("Stack()", stack.to_string())
     }
 
     #[test]
     fn single_element_stack() {
         let stack = Stack::empty().append("hello");
-        assert_eq!("Stack(hello)", format!("{}", stack.to_string()))
+        assert_eq!("Stack(hello)", stack.to_string())
     }
 
     #[test]
     fn two_elements_stack() {
         let stack = Stack::empty().append("hello").append("bye");
-        assert_eq!("Stack(hello, bye)", format!("{}", stack.to_string()))
+        assert_eq!("Stack(hello, bye)", stack.to_string())
     }
 
     #[test]
     fn shows_its_strings() {
         let stack = stack_abcd();
-        assert_eq!("Stack(a, b, c, d)", format!("{}", stack.to_string()))
+        assert_eq!("Stack(a, b, c, d)", stack.to_string())
     }
 
     #[test]
@@ -0,0 +1,202 @@
+use keepass::db::{Group, Node};
+use keepass::Database;
+
+use crate::diff::changes::{EntryChange, GroupChange, GroupDiff};
+use crate::diff::group;
+use crate::locate::{entry_in, extend, group_at, group_at_mut, is_entry};
+
+/// The result of a sync, every path starts with the name of the root group and ends with the
+/// name of the group or the title of the entry
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Groups and entries of B that were copied into A
+    pub added: Vec<Vec<String>>,
+    /// Groups and entries of B that were left out, as their UUID already exists somewhere else
+    /// in A. They were moved or renamed rather than added, and copying them would duplicate the
+    /// UUID. This includes groups and entries inside of copied groups.
+    pub skipped: Vec<Vec<String>>,
+}
+
+/// Copies every group and entry that only exists in B into A, at the same path. The copies keep
+/// their UUIDs, times and history. Nothing that exists in A is changed or removed.
+pub fn sync(a: &mut Database, b: &Database) -> SyncReport {
+    let changes = group::Group::from_keepass(&a.root).changes(&group::Group::from_keepass(&b.root));
+    let mut sync = SyncReport::default();
+    sync_group(
+        a,
+        b,
+        &changes,
+        std::slice::from_ref(&changes.name),
+        &mut sync,
+    );
+    sync
+}
+
+fn sync_group(
+    a: &mut Database,
+    b: &Database,
+    diff: &GroupDiff,
+    path: &[String],
+    sync: &mut SyncReport,
+) {
+    let source = match group_at(&b.root, path) {
+        Some(source) => source,
+        None => return,
+    };
+    let mut added = Vec::new();
+    for change in &diff.groups {
+        match change {
+            GroupChange::Added(info) => {
+                let found = source.children.iter().find_map(|node| match node {
+                    Node::Group(child) if child.name == info.name => Some(child),
+                    _ => None,
+                });
+                if let Some(child) = found {
+                    added.push((extend(path, &info.name), Node::Group(child.clone())));
+                }
+            }
+            GroupChange::Modified(diff) => sync_group(a, b, diff, &extend(path, &diff.name), sync),
+            GroupChange::Removed(_) => {}
+        }
+    }
+    for change in &diff.entries {
+        if let EntryChange::Added(info) = change {
+            if let Some(entry) = entry_in(source, &info.uuid, &info.title) {
+                added.push((extend(path, &info.title), Node::Entry(entry.clone())));
+            }
+        }
+    }
+
+    for (child_path, mut node) in added {
+        if contains(&a.root, &uuid(&node)) {
+            sync.skipped.push(child_path);
+            continue;
+        }
+        if let Node::Group(group) = &mut node {
+            prune(group, &a.root, &child_path, &mut sync.skipped);
+        }
+        if let Some(target) = group_at_mut(&mut a.root, path) {
+            target.children.push(node);
+            sync.added.push(child_path);
+        }
+    }
+}
+
+/// Removes the groups and entries from a copied group whose UUID already exists in A, and adds
+/// their paths to `skipped`
+fn prune(group: &mut Group, a: &Group, path: &[String], skipped: &mut Vec<Vec<String>>) {
+    group.children.retain(|node| {
+        let exists = contains(a, &uuid(node));
+        if exists {
+            skipped.push(extend(path, &name(node)));
+        }
+        !exists
+    });
+    for node in &mut group.children {
+        if let Node::Group(child) = node {
+            let child_path = extend(path, &child.name);
+            prune(child, a, &child_path, skipped);
+        }
+    }
+}
+
+fn uuid(node: &Node) -> String {
+    match node {
+        Node::Group(group) => group.uuid.to_string(),
+        Node::Entry(entry) => entry.uuid.to_string(),
+    }
+}
+
+fn name(node: &Node) -> String {
+    match node {
+        Node::Group(group) => group.name.clone(),
+        Node::Entry(entry) => entry.get_title().unwrap_or_default().to_owned(),
+    }
+}
+
+/// Whether a group or entry with the UUID is somewhere in the group, including itself
+fn contains(group: &Group, uuid: &str) -> bool {
+    group.uuid.to_string() == uuid
+        || group.children.iter().any(|node| match node {
+            Node::Group(child) => contains(child, uuid),
+            Node::Entry(_) => is_entry(node, uuid),
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::entry;
+
+    fn path(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn copies_what_only_exists_in_b() {
+        let shared = entry("Shared", "");
+        let mut a = Database::new(Default::default());
+        a.root.add_child(shared.clone());
+        let mut b = Database::new(Default::default());
+        b.root.add_child(shared);
+        let new = entry("New", "");
+        let mut group = Group::new("Group");
+        group.add_child(entry("Inside", ""));
+        b.root.add_child(new.clone());
+        b.root.add_child(group.clone());
+
+        let sync = sync(&mut a, &b);
+
+        assert_eq!(
+            vec![path(&["Root", "Group"]), path(&["Root", "New"])],
+            sync.added
+        );
+        assert!(a.root.children.contains(&Node::Entry(new)));
+        assert!(a.root.children.contains(&Node::Group(group)));
+    }
+
+    #[test]
+    fn skips_moved_entries() {
+        let moved = entry("Moved", "");
+        let mut a = Database::new(Default::default());
+        let mut group = Group::new("Group");
+        group.add_child(moved.clone());
+        a.root.add_child(group);
+        let mut b = Database::new(Default::default());
+        b.root.add_child(Group::new("Group"));
+        b.root.add_child(moved);
+
+        let sync = sync(&mut a, &b);
+
+        assert_eq!(vec![path(&["Root", "Moved"])], sync.skipped);
+        assert!(sync.added.is_empty());
+    }
+
+    #[test]
+    fn skips_entries_moved_into_new_groups() {
+        let moved = entry("Moved", "");
+        let mut a = Database::new(Default::default());
+        a.root.add_child(moved.clone());
+        let mut b = Database::new(Default::default());
+        let new = entry("New", "");
+        let mut inner = Group::new("Inner");
+        inner.add_child(moved);
+        let mut group = Group::new("Group");
+        group.add_child(new.clone());
+        group.add_child(inner);
+        let mut expected = group.clone();
+        if let Node::Group(inner) = &mut expected.children[1] {
+            inner.children.clear();
+        }
+        b.root.add_child(group);
+
+        let sync = sync(&mut a, &b);
+
+        assert_eq!(vec![path(&["Root", "Group"])], sync.added);
+        assert_eq!(
+            vec![path(&["Root", "Group", "Inner", "Moved"])],
+            sync.skipped
+        );
+        assert!(a.root.children.contains(&Node::Group(expected)));
+    }
+}
@@ -0,0 +1,16 @@
+//! Fixtures shared by the tests of the modules that change databases
+
+use keepass::db::{Entry, Value};
+
+/// An entry with the given title and protected password, like KeePass creates them
+pub(crate) fn entry(title: &str, password: &str) -> Entry {
+    let mut entry = Entry::new();
+    entry
+        .fields
+        .insert("Title".to_owned(), Value::Unprotected(title.to_owned()));
+    entry.fields.insert(
+        "Password".to_owned(),
+        Value::Protected(password.as_bytes().into()),
+    );
+    entry
+}